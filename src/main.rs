@@ -1,8 +1,23 @@
+#![allow(clippy::new_without_default)]
+
 pub mod system;
 
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
 use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::{Parser, ValueEnum};
 
-use clap::Parser;
+use crate::system::System;
+use crate::system::boot_rom::BootRom;
+use crate::system::cartridge::Cartridge;
+use crate::system::cartridge::save::SaveFile;
+use crate::system::cpu::IllegalOpcodeMode;
+use crate::system::model::Model;
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -10,13 +25,105 @@ use clap::Parser;
 struct Args {
     /// Filename to load
     file: Option<PathBuf>,
+    /// Battery-backed RAM is saved here, instead of next to the ROM with a .sav extension
+    #[arg(long, value_name = "FILE")]
+    save: Option<PathBuf>,
+    /// Run this DMG or CGB boot ROM before the cartridge
+    #[arg(long, value_name = "FILE")]
+    boot_rom: Option<PathBuf>,
+    /// Log CPU state before each instruction to this file, in gameboy-doctor format
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+    /// Stop with an error on HALT with interrupts disabled and nothing enabled in IE
+    #[arg(long)]
+    detect_lockups: bool,
+    /// What the CPU does on an undefined opcode
+    #[arg(long, value_enum, default_value_t = IllegalOpcode::Error)]
+    illegal_opcode: IllegalOpcode,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum IllegalOpcode {
+    /// Hang the CPU as hardware does, while the rest of the system keeps running
+    Lock,
+    /// Stop emulation with an error
+    Error,
+}
 
+impl From<IllegalOpcode> for IllegalOpcodeMode {
+    fn from(mode: IllegalOpcode) -> IllegalOpcodeMode {
+        match mode {
+            IllegalOpcode::Lock => IllegalOpcodeMode::Lock,
+            IllegalOpcode::Error => IllegalOpcodeMode::Error,
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let Some(path) = args.file.as_deref() else { return Ok(()) };
+    println!("Loading: {}", path.display());
+    let mut cartridge = Cartridge::from_file(path, args.boot_rom.is_some())?;
+    if let Some(header) = cartridge.header() { println!("{}", header) }
+    for warning in cartridge.warnings() { println!("Warning: {}", warning) }
+    let save_file = if cartridge.has_battery() {
+        let save_file = match args.save.clone() {
+            Some(save_path) => SaveFile::new(save_path),
+            None => SaveFile::for_rom(path),
+        };
+        if save_file.load(&mut cartridge)? { println!("Loaded save: {}", save_file.path().display()) }
+        Some(save_file)
+    }
+    else { None };
+
+    let mut system = System::with_illegal_opcode_mode(args.illegal_opcode.into());
+    system.insert_cartridge(cartridge);
+    match args.boot_rom.as_deref() {
+        Some(boot_rom_path) => system.map_boot_rom(BootRom::new(fs::read(boot_rom_path)?)?),
+        None => system.set_post_boot_state(Model::DMG),
+    }
+    system.set_lockup_detection(args.detect_lockups);
+    if let Some(trace_path) = args.trace.as_deref() {
+        system.set_trace(Some(Box::new(BufWriter::new(File::create(trace_path)?))));
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed))?;
+    let result = run_system(&mut system, save_file.as_ref(), &stop);
+    // save is written however emulation stopped, without hiding the reason it stopped
+    if let Some(save_file) = &save_file {
+        if let Err(error) = save_file.write(system.cartridge_mut()) {
+            if result.is_ok() { return Err(error.into()) }
+            eprintln!("Error writing save: {}", error);
+        }
+    }
+    result
+}
+
+/// Instructions run between checks for Ctrl-C, well under a frame's worth
+const STOP_INTERVAL: u32 = 1_000;
+/// Instructions run between checks for changes to battery-backed RAM
+const SAVE_INTERVAL: u32 = 1_000_000;
+
+/// Runs until an error, or until `stop` is set by the Ctrl-C handler
+fn run_system(system: &mut System, save_file: Option<&SaveFile>, stop: &AtomicBool) -> Result<(), Box<dyn Error>> {
+    let mut since_save = 0;
+    while !stop.load(Ordering::Relaxed) {
+        for _ in 0..STOP_INTERVAL {
+            system.step()?;
+        }
+        since_save += STOP_INTERVAL;
+        if since_save >= SAVE_INTERVAL {
+            since_save = 0;
+            if let Some(save_file) = save_file { save_file.write_if_changed(system.cartridge_mut())? }
+        }
+    }
+    Ok(())
+}
 
 fn main() {
     let args = Args::parse();
-    if let Some(path) = args.file.as_deref() {
-        println!("Loading: {}", path.display());
+    if let Err(error) = run(&args) {
+        eprintln!("Error: {}", error);
+        process::exit(1);
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,32 @@
+pub mod boot_rom;
+#[cfg(test)]
+pub mod boot_rom_tests;
+pub mod cartridge;
 pub mod cpu;
+pub mod interrupts;
+#[cfg(test)]
+pub mod interrupts_tests;
 pub mod memory;
+pub mod model;
+pub mod ppu;
+#[cfg(test)]
+pub mod ppu_tests;
 pub mod timer;
+#[cfg(test)]
 pub mod timer_tests;
 
-use crate::system::cpu::CPU;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+
+use crate::system::boot_rom::BootRom;
+use crate::system::cartridge::Cartridge;
+use crate::system::cartridge::infrared::InfraredPort;
+use crate::system::cpu::{CPU, CpuError, IllegalOpcodeMode};
+use crate::system::interrupts::{Interrupt, InterruptController};
 use crate::system::memory::Memory;
+use crate::system::model::{Model, POST_BOOT_IO};
+use crate::system::ppu::Ppu;
 use crate::system::timer::Timer;
 
 pub struct System{
@@ -12,19 +34,108 @@ pub struct System{
     /// of any flags, registers, and memory
     memory: Memory,
     cpu: CPU,
-    // cartridge: Cartridge
 }
 
-/// struct that abstracts the ROM file as a cartridge connected to System
-/// Max size is 32kB
-pub struct Cartridge {
+/// Reasons the system can't continue
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SystemError {
+    Cpu(CpuError),
+    BootRomLockup { pc: u16 }, // boot ROM rejected the cartridge and is spinning forever
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SystemError::Cpu(error) => write!(f, "{}", error),
+            SystemError::BootRomLockup { pc } => write!(f,
+                "boot ROM locked up at ${:04X}, the cartridge header failed its logo or checksum check", pc),
+        }
+    }
+}
+
+impl Error for SystemError {}
 
+impl From<CpuError> for SystemError {
+    fn from(error: CpuError) -> SystemError {
+        SystemError::Cpu(error)
+    }
 }
 
 impl System {
     pub fn new() -> System{
-        let cpu = CPU::new();
+        System::with_illegal_opcode_mode(IllegalOpcodeMode::Error)
+    }
+
+    pub fn with_illegal_opcode_mode(mode: IllegalOpcodeMode) -> System {
+        let cpu = CPU::with_illegal_opcode_mode(mode);
         let memory = Memory::new();
         System {memory, cpu}
     }
-}
\ No newline at end of file
+
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.memory.cartridge = cartridge;
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.memory.cartridge
+    }
+
+    /// Starts from power-on with the boot ROM overlaid on the cartridge,
+    /// as an alternative to set_post_boot_state
+    pub fn map_boot_rom(&mut self, boot_rom: BootRom) {
+        self.memory.map_boot_rom(boot_rom);
+        self.cpu.set_power_on_state();
+    }
+
+    /// Skips the boot ROM, leaving the system in the state it would have at 0x0100.
+    /// The ROM should be loaded first, as the flags depend on its header.
+    pub fn set_post_boot_state(&mut self, model: Model) {
+        self.memory.set_post_boot_state(model);
+        self.cpu.set_post_boot_state(model, &self.memory);
+    }
+
+    /// Report HALT that can never wake as an error, see CPU::set_lockup_detection
+    pub fn set_lockup_detection(&mut self, enabled: bool) {
+        self.cpu.set_lockup_detection(enabled);
+    }
+
+    /// Log the CPU state before each instruction, see CPU::set_trace
+    pub fn set_trace(&mut self, trace: Option<Box<dyn Write>>) {
+        self.cpu.set_trace(trace);
+    }
+
+    /// Screen contents as shades 0 (lightest) to 3 (darkest), row by row,
+    /// SCREEN_WIDTH x SCREEN_HEIGHT. Each line is filled as the PPU finishes drawing it.
+    pub fn framebuffer(&self) -> &[u8] {
+        self.memory.ppu.framebuffer()
+    }
+
+    /// True while the cartridge's rumble motor is switched on
+    pub fn rumble(&self) -> bool {
+        self.memory.cartridge.rumble()
+    }
+
+    /// Tilts the cartridge's accelerometer, if it has one, by x and y in g.
+    /// Games latch the values themselves, so they can be updated at any time.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.memory.cartridge.set_tilt(x, y);
+    }
+
+    /// Connects the cartridge's infrared port, if it has one
+    pub fn connect_infrared(&mut self, port: Box<dyn InfraredPort>) {
+        self.memory.cartridge.connect_infrared(port);
+    }
+
+    /// Runs a single instruction, returning its opcode
+    pub fn step(&mut self) -> Result<u8, SystemError> {
+        let pc = self.cpu.get_pc();
+        let opcode = self.cpu.run(&mut self.memory)?;
+        // the boot ROM hangs with a relative jump to itself when the cartridge fails its checks,
+        // JR NZ on the DMG's logo and header checksum checks
+        let relative_jump = matches!(opcode, 0x18 | 0x20 | 0x28 | 0x30 | 0x38);
+        if relative_jump && self.cpu.get_pc() == pc && self.memory.boot_rom_mapped() {
+            return Err(SystemError::BootRomLockup { pc });
+        }
+        Ok(opcode)
+    }
+}
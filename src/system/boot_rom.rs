@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+
+/// Boot ROM overlaid on the start of the cartridge until 0xFF50 is written.
+/// DMG boot ROMs cover 0x0000-0x00FF, CGB boot ROMs also cover 0x0200-0x08FF,
+/// leaving the cartridge header at 0x0100-0x01FF visible.
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+pub const DMG_BOOT_ROM_LEN: usize = 0x100;
+pub const CGB_BOOT_ROM_LEN: usize = 0x900;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BootRomError {
+    InvalidLength(usize),
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BootRomError::InvalidLength(len) => write!(f,
+                "boot ROM is {} bytes, expected {} (DMG) or {} (CGB)", len, DMG_BOOT_ROM_LEN, CGB_BOOT_ROM_LEN),
+        }
+    }
+}
+
+impl Error for BootRomError {}
+
+impl BootRom {
+    pub fn new(data: Vec<u8>) -> Result<BootRom, BootRomError> {
+        match data.len() {
+            DMG_BOOT_ROM_LEN | CGB_BOOT_ROM_LEN => Ok(BootRom { data }),
+            len => Err(BootRomError::InvalidLength(len)),
+        }
+    }
+
+    /// Byte at the given address, or None where the cartridge shows through
+    pub fn read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0100..=0x01FF => None,
+            _ => self.data.get(addr as usize).copied(),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use super::{System, SystemError, Memory};
+use super::boot_rom::{BootRom, BootRomError};
+
+#[test]
+fn boot_rom_overlay() {
+    let mut memory = Memory::new();
+    memory.poke(0x0000, 0x11);
+    memory.poke(0x0100, 0x22);
+    memory.poke(0x0200, 0x33);
+
+    let mut boot_rom = vec![0xAA; 0x900];
+    boot_rom[0x0100] = 0xBB; // hidden behind the cartridge header
+    memory.map_boot_rom(BootRom::new(boot_rom).unwrap());
+    assert_eq!(memory.read_byte(0x0000), 0xAA);
+    assert_eq!(memory.read_byte(0x0100), 0x22);
+    assert_eq!(memory.read_byte(0x0200), 0xAA);
+    assert_eq!(memory.read_byte(0x0900), 0x00);
+
+    // writes with bit 0 clear leave the boot ROM mapped
+    memory.write_byte(0xFF50, 0x00);
+    assert_eq!(memory.read_byte(0x0000), 0xAA);
+
+    memory.write_byte(0xFF50, 0x01);
+    assert!(!memory.boot_rom_mapped());
+    assert_eq!(memory.read_byte(0x0000), 0x11);
+    assert_eq!(memory.read_byte(0x0200), 0x33);
+}
+
+#[test]
+fn boot_rom_length() {
+    assert!(BootRom::new(vec![0; 0x100]).is_ok());
+    assert_eq!(BootRom::new(vec![0; 0x200]).err(), Some(BootRomError::InvalidLength(0x200)));
+}
+
+#[test]
+fn boot_rom_lockup() {
+    let mut system = System::new();
+    let mut boot_rom = vec![0x00; 0x100];
+    boot_rom[0x02] = 0x18; // JR -2, as taken on a failed logo check
+    boot_rom[0x03] = 0xFE;
+    system.map_boot_rom(BootRom::new(boot_rom).unwrap());
+
+    system.step().unwrap();
+    system.step().unwrap();
+    assert_eq!(system.step(), Err(SystemError::BootRomLockup { pc: 0x02 }));
+
+    // the same loop is allowed once the cartridge is running
+    system.memory.write_byte(0xFF50, 0x01);
+    system.memory.poke(0x02, 0x18);
+    system.memory.poke(0x03, 0xFE);
+    assert_eq!(system.step(), Ok(0x18));
+}
+
+#[test]
+fn boot_rom_lockup_conditional() {
+    // the DMG boot ROM's logo check fails into JR NZ,-2
+    let mut system = System::new();
+    let mut boot_rom = vec![0x00; 0x100];
+    boot_rom[0x00] = 0x20;
+    boot_rom[0x01] = 0xFE;
+    system.map_boot_rom(BootRom::new(boot_rom).unwrap());
+    assert_eq!(system.step(), Err(SystemError::BootRomLockup { pc: 0x00 }));
+}
+
+#[test]
+fn boot_rom_power_on_ime() {
+    // IME is clear at power-on, so a pending interrupt isn't serviced
+    let mut system = System::new();
+    system.map_boot_rom(BootRom::new(vec![0x00; 0x100]).unwrap());
+    system.memory.write_byte(0xFFFF, 0x01);
+    system.memory.write_byte(0xFF0F, 0x01);
+    system.step().unwrap();
+    assert_eq!(system.cpu.get_pc(), 0x01);
+}
@@ -0,0 +1,387 @@
+pub mod eeprom;
+pub mod header;
+pub mod huc1;
+pub mod huc3;
+pub mod infrared;
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod mbc7;
+pub mod rtc;
+pub mod save;
+#[cfg(test)]
+pub mod cartridge_tests;
+#[cfg(test)]
+pub mod huc1_tests;
+#[cfg(test)]
+pub mod huc3_tests;
+#[cfg(test)]
+pub mod mbc1_tests;
+#[cfg(test)]
+pub mod mbc2_tests;
+#[cfg(test)]
+pub mod mbc3_tests;
+#[cfg(test)]
+pub mod mbc5_tests;
+#[cfg(test)]
+pub mod mbc7_tests;
+#[cfg(test)]
+pub mod save_tests;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::system::cartridge::eeprom::EEPROM_LEN;
+use crate::system::cartridge::header::{Header, Mbc};
+use crate::system::cartridge::huc1::HuC1;
+use crate::system::cartridge::huc3::{HuC3, HUC3_TRAILER_LEN};
+use crate::system::cartridge::infrared::InfraredPort;
+use crate::system::cartridge::mbc1::Mbc1;
+use crate::system::cartridge::mbc2::{Mbc2, MBC2_RAM_LEN};
+use crate::system::cartridge::mbc3::Mbc3;
+use crate::system::cartridge::mbc5::Mbc5;
+use crate::system::cartridge::mbc7::Mbc7;
+use crate::system::cartridge::rtc::{RtcClock, RTC_TRAILER_LEN};
+
+/// struct that abstracts the ROM file as a cartridge connected to System
+pub struct Cartridge {
+    header: Option<Header>,
+    controller: Controller,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_written: bool, // RAM changed since the last save
+    warnings: Vec<CartridgeError>, // header mismatches that didn't stop the cartridge loading
+}
+
+/// Memory bank controller, mapping the cartridge's ROM and RAM onto the bus
+enum Controller {
+    None,
+    Mbc1(Mbc1),
+    Mbc2(Mbc2),
+    Mbc3(Mbc3),
+    Mbc5(Mbc5),
+    Mbc7(Mbc7),
+    HuC1(HuC1),
+    HuC3(HuC3),
+}
+
+/// Reasons a ROM image can't be loaded as a cartridge
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    Truncated { len: usize }, // too short to hold a header
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    UnsupportedMbc(Mbc),
+    RomSizeMismatch { header: usize, actual: usize },
+    RamSizeMismatch { cartridge_type: u8, ram_size: usize },
+    LogoMismatch,
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+    SaveSizeMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(error) => write!(f, "couldn't read ROM: {}", error),
+            CartridgeError::Truncated { len } => write!(f, "ROM is {} bytes, too short to hold a header", len),
+            CartridgeError::UnknownCartridgeType(code) => write!(f, "unknown cartridge type ${:02X}", code),
+            CartridgeError::UnknownRomSize(code) => write!(f, "unknown ROM size code ${:02X}", code),
+            CartridgeError::UnknownRamSize(code) => write!(f, "unknown RAM size code ${:02X}", code),
+            CartridgeError::UnsupportedMbc(mbc) => write!(f, "{:?} cartridges aren't supported", mbc),
+            CartridgeError::RomSizeMismatch { header, actual } => write!(f,
+                "header gives a ROM size of {} bytes, but the image is {} bytes", header, actual),
+            CartridgeError::RamSizeMismatch { cartridge_type, ram_size } => write!(f,
+                "header gives {} bytes of RAM, but cartridge type ${:02X} has none", ram_size, cartridge_type),
+            CartridgeError::LogoMismatch => write!(f, "Nintendo logo in the header doesn't match"),
+            CartridgeError::HeaderChecksum { expected, actual } => write!(f,
+                "header checksum is ${:02X}, but the header sums to ${:02X}", expected, actual),
+            CartridgeError::GlobalChecksum { expected, actual } => write!(f,
+                "global checksum is ${:04X}, but the image sums to ${:04X}", expected, actual),
+            CartridgeError::SaveSizeMismatch { expected, actual } => write!(f,
+                "save file is {} bytes, but the cartridge saves {} bytes", actual, expected),
+        }
+    }
+}
+
+impl Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> CartridgeError {
+        CartridgeError::Io(error)
+    }
+}
+
+impl Cartridge {
+    /// ROM image without a memory bank controller, loaded without parsing its header
+    pub fn new(rom: Vec<u8>) -> Cartridge {
+        // ROM without a memory bank controller fills 0x0000-0x7FFF
+        let mut rom = rom;
+        rom.resize(0x8000, 0xFF);
+        Cartridge { header: None, controller: Controller::None, rom, ram: Vec::new(), ram_written: false, warnings: Vec::new() }
+    }
+
+    /// Cartridge with no ROM contents, for running code written into memory
+    pub fn empty() -> Cartridge {
+        Cartridge::new(vec![0; 0x8000])
+    }
+
+    /// ROM image with a valid header, which decides the hardware on the cartridge.
+    /// Real-time clocks follow the host's clock.
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        Cartridge::from_bytes_with_clock(rom, RtcClock::WallClock)
+    }
+
+    pub fn from_bytes_with_clock(rom: Vec<u8>, clock: RtcClock) -> Result<Cartridge, CartridgeError> {
+        Cartridge::load(rom, clock, false)
+    }
+
+    /// ROM image to be started by a boot ROM, which is left to reject a bad logo or header checksum
+    pub fn from_bytes_for_boot_rom(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        Cartridge::load(rom, RtcClock::WallClock, true)
+    }
+
+    fn load(rom: Vec<u8>, clock: RtcClock, boot_rom: bool) -> Result<Cartridge, CartridgeError> {
+        let header = Header::parse(&rom)?;
+        let warnings = header.validate(&rom, boot_rom)?;
+        let controller = match header.cartridge_type.mbc {
+            Mbc::None => Controller::None,
+            Mbc::Mbc1 => Controller::Mbc1(Mbc1::new(Mbc1::detect_multicart(&rom))),
+            Mbc::Mbc2 => Controller::Mbc2(Mbc2::new()),
+            Mbc::Mbc3 => Controller::Mbc3(Mbc3::new(header.cartridge_type.timer, clock)),
+            Mbc::Mbc5 => Controller::Mbc5(Mbc5::new(header.cartridge_type.rumble)),
+            Mbc::Mbc7 => Controller::Mbc7(Mbc7::new()),
+            Mbc::HuC1 => Controller::HuC1(HuC1::new()),
+            Mbc::HuC3 => Controller::HuC3(HuC3::new(clock)),
+            mbc => return Err(CartridgeError::UnsupportedMbc(mbc)),
+        };
+        let ram_size = match controller {
+            Controller::Mbc2(_) => MBC2_RAM_LEN,
+            _ => header.ram_size,
+        };
+        let ram = vec![0; ram_size];
+        Ok(Cartridge { header: Some(header), controller, rom, ram, ram_written: false, warnings })
+    }
+
+    pub fn from_file(path: &Path, boot_rom: bool) -> Result<Cartridge, CartridgeError> {
+        let rom = fs::read(path)?;
+        if boot_rom { Cartridge::from_bytes_for_boot_rom(rom) } else { Cartridge::from_bytes(rom) }
+    }
+
+    /// Header of a cartridge loaded with from_bytes or from_file
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Problems found in the header that the cartridge was loaded in spite of
+    pub fn warnings(&self) -> &[CartridgeError] {
+        &self.warnings
+    }
+
+    /// 0x0000-0x7FFF
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        self.rom[self.rom_offset(addr)]
+    }
+
+    fn rom_offset(&self, addr: u16) -> usize {
+        let offset = match &self.controller {
+            Controller::None => addr as usize,
+            Controller::Mbc1(mbc) => mbc.rom_offset(addr),
+            Controller::Mbc2(mbc) => mbc.rom_offset(addr),
+            Controller::Mbc3(mbc) => mbc.rom_offset(addr),
+            Controller::Mbc5(mbc) => mbc.rom_offset(addr),
+            Controller::Mbc7(mbc) => mbc.rom_offset(addr),
+            Controller::HuC1(mbc) => mbc.rom_offset(addr),
+            Controller::HuC3(mbc) => mbc.rom_offset(addr),
+        };
+        // bank numbers wrap around to the size of the ROM
+        offset % self.rom.len()
+    }
+
+    /// ROM can't be written, so writes go to the controller's registers
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match &mut self.controller {
+            Controller::None => {}
+            Controller::Mbc1(mbc) => mbc.write_register(addr, byte),
+            Controller::Mbc2(mbc) => mbc.write_register(addr, byte),
+            Controller::Mbc3(mbc) => mbc.write_register(addr, byte),
+            Controller::Mbc5(mbc) => mbc.write_register(addr, byte),
+            Controller::Mbc7(mbc) => mbc.write_register(addr, byte),
+            Controller::HuC1(mbc) => mbc.write_register(addr, byte),
+            Controller::HuC3(mbc) => mbc.write_register(addr, byte),
+        }
+    }
+
+    /// 0xA000-0xBFFF, reading 0xFF when RAM is absent or disabled
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        // registers the controller maps in place of RAM
+        let mapped = match &self.controller {
+            Controller::Mbc3(mbc) => mbc.read_rtc(),
+            Controller::Mbc7(mbc) => Some(mbc.read_ram(addr)),
+            Controller::HuC1(mbc) => mbc.read_ir(),
+            Controller::HuC3(mbc) => mbc.read_mapped(),
+            _ => None,
+        };
+        if let Some(byte) = mapped { return byte }
+        match (self.ram_offset(addr), &self.controller) {
+            // MBC2 RAM only stores the lower half of each byte
+            (Some(offset), Controller::Mbc2(_)) => 0xF0 | self.ram[offset],
+            (Some(offset), _) => self.ram[offset],
+            (None, _) => 0xFF,
+        }
+    }
+
+    pub fn write_ram(&mut self, addr: u16, byte: u8) {
+        let byte = match self.controller {
+            Controller::Mbc2(_) => byte & 0x0F,
+            _ => byte,
+        };
+        // registers the controller maps in place of RAM
+        let mapped = match &mut self.controller {
+            Controller::Mbc3(mbc) => {
+                let written = mbc.write_rtc(byte);
+                self.ram_written |= written;
+                written
+            }
+            Controller::Mbc7(mbc) => {
+                self.ram_written |= mbc.write_ram(addr, byte);
+                true
+            }
+            Controller::HuC1(mbc) => mbc.write_ir(byte),
+            Controller::HuC3(mbc) => match mbc.write_mapped(byte) {
+                Some(changed) => {
+                    self.ram_written |= changed;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+        if mapped { return }
+        if let Some(offset) = self.ram_offset(addr) {
+            self.ram[offset] = byte;
+            self.ram_written = true;
+        }
+    }
+
+    /// Advances any real-time clock running on emulated time
+    pub fn update_cycle(&mut self, cycles: u8) {
+        match &mut self.controller {
+            Controller::Mbc3(Mbc3 { rtc: Some(rtc), .. }) => rtc.update_cycle(cycles),
+            Controller::HuC3(mbc) => mbc.clock.update_cycle(cycles),
+            _ => {}
+        }
+    }
+
+    /// True if the cartridge keeps its RAM or clock while switched off
+    pub fn has_battery(&self) -> bool {
+        self.header.as_ref().is_some_and(|header| header.cartridge_type.battery)
+    }
+
+    /// True if RAM, the clock or the EEPROM has been written since the last call to save_data
+    pub fn ram_written(&self) -> bool {
+        self.ram_written
+    }
+
+    /// Contents of a save file: RAM, followed by the clock state on cartridges with one.
+    /// MBC7 cartridges have no RAM, and save their EEPROM instead.
+    pub fn save_data(&mut self) -> Vec<u8> {
+        self.ram_written = false;
+        let mut data = self.ram.clone();
+        match &mut self.controller {
+            Controller::Mbc3(Mbc3 { rtc: Some(rtc), .. }) => data.extend_from_slice(&rtc.to_trailer()),
+            Controller::HuC3(mbc) => data.extend_from_slice(&mbc.to_trailer()),
+            Controller::Mbc7(mbc) => data.extend_from_slice(&mbc.eeprom.to_bytes()),
+            _ => {}
+        }
+        data
+    }
+
+    /// Restores RAM and clock state from a save file written by save_data.
+    /// A clock trailer is optional, so saves from emulators without one can be loaded.
+    pub fn load_save_data(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        let ram_len = self.ram.len();
+        match &mut self.controller {
+            Controller::Mbc3(Mbc3 { rtc: Some(rtc), .. }) if data.len() == ram_len + RTC_TRAILER_LEN => {
+                rtc.load_trailer(data[ram_len..].try_into().unwrap());
+            }
+            Controller::HuC3(mbc) if data.len() == ram_len + HUC3_TRAILER_LEN => {
+                mbc.load_trailer(data[ram_len..].try_into().unwrap());
+            }
+            Controller::Mbc7(mbc) if data.len() == ram_len + EEPROM_LEN => mbc.eeprom.load_bytes(&data[ram_len..]),
+            Controller::Mbc7(_) => {
+                return Err(CartridgeError::SaveSizeMismatch { expected: ram_len + EEPROM_LEN, actual: data.len() });
+            }
+            _ if data.len() == ram_len => {}
+            _ => return Err(CartridgeError::SaveSizeMismatch { expected: ram_len, actual: data.len() }),
+        }
+        self.ram.copy_from_slice(&data[..ram_len]);
+        Ok(())
+    }
+
+    /// True while a rumble cartridge's motor is switched on
+    pub fn rumble(&self) -> bool {
+        match &self.controller {
+            Controller::Mbc5(mbc) => mbc.rumble(),
+            _ => false,
+        }
+    }
+
+    pub fn set_rtc_clock(&mut self, clock: RtcClock) {
+        match &mut self.controller {
+            Controller::Mbc3(Mbc3 { rtc: Some(rtc), .. }) => rtc.set_clock(clock),
+            Controller::HuC3(mbc) => mbc.clock.set_clock(clock),
+            _ => {}
+        }
+    }
+
+    /// Sets an MBC7 cartridge's accelerometer, in g along each axis. Ignored by other cartridges.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        if let Controller::Mbc7(mbc) = &mut self.controller {
+            mbc.set_tilt(x, y);
+        }
+    }
+
+    /// True if the cartridge has an infrared LED and receiver
+    pub fn has_infrared(&self) -> bool {
+        matches!(self.controller, Controller::HuC1(_) | Controller::HuC3(_))
+    }
+
+    /// Connects the cartridge's infrared port to another endpoint, such as one end of an InfraredLink.
+    /// Ignored by cartridges without one.
+    pub fn connect_infrared(&mut self, port: Box<dyn InfraredPort>) {
+        match &mut self.controller {
+            Controller::HuC1(mbc) => mbc.infrared.connect(port),
+            Controller::HuC3(mbc) => mbc.infrared.connect(port),
+            _ => {}
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.ram.is_empty() { return None }
+        let offset = match &self.controller {
+            Controller::None => Some(addr as usize - 0xA000),
+            Controller::Mbc1(mbc) => mbc.ram_offset(addr),
+            Controller::Mbc2(mbc) => mbc.ram_offset(addr),
+            Controller::Mbc3(mbc) => mbc.ram_offset(addr),
+            Controller::Mbc5(mbc) => mbc.ram_offset(addr),
+            Controller::Mbc7(_) => None,
+            Controller::HuC1(mbc) => mbc.ram_offset(addr),
+            Controller::HuC3(mbc) => mbc.ram_offset(addr),
+        };
+        // smaller RAM chips are mirrored across the bank
+        offset.map(|offset| offset % self.ram.len())
+    }
+
+    /// Overwrites ROM contents in the currently mapped bank, as a debugger patching the program would
+    pub fn patch_rom(&mut self, addr: u16, byte: u8) {
+        let offset = self.rom_offset(addr);
+        self.rom[offset] = byte;
+    }
+}
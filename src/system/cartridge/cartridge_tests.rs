@@ -0,0 +1,117 @@
+use super::{Cartridge, CartridgeError};
+use super::header::{self, CartridgeType, CgbSupport, Destination, Header, Licensee, Mbc, NINTENDO_LOGO};
+use super::rtc::RtcClock;
+
+/// ROM image of the given size with a valid header, after applying changes to the header
+pub fn rom_image(len: usize, header_bytes: &[(usize, u8)]) -> Vec<u8> {
+    let mut rom = vec![0; len];
+    rom[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+    rom[0x0134..0x0139].copy_from_slice(b"ORION");
+    rom[0x0148] = (len / 0x8000).trailing_zeros() as u8;
+    for &(addr, byte) in header_bytes { rom[addr] = byte }
+    fix_checksums(&mut rom);
+    rom
+}
+
+pub fn fix_checksums(rom: &mut [u8]) {
+    rom[0x014D] = header::header_checksum(rom);
+    let global_checksum = header::global_checksum(rom);
+    rom[0x014E] = (global_checksum >> 8) as u8;
+    rom[0x014F] = global_checksum as u8;
+}
+
+/// Cartridge of the given type with as much RAM as its controller can bank, an emulated clock,
+/// and each 16 KiB ROM bank holding its bank number as a little-endian u16 at offset 1
+pub fn banked_cartridge(cartridge_type: u8, rom_len: usize) -> Cartridge {
+    let ram_size_code = match CartridgeType::from_code(cartridge_type).unwrap() {
+        CartridgeType { ram: false, .. } | CartridgeType { mbc: Mbc::Mbc2 | Mbc::Mbc7, .. } => 0x00,
+        CartridgeType { mbc: Mbc::Mbc5, .. } => 0x04,
+        _ => 0x03,
+    };
+    let mut rom = rom_image(rom_len, &[(0x0147, cartridge_type), (0x0149, ram_size_code)]);
+    for bank in 0..rom_len / 0x4000 {
+        rom[bank * 0x4000 + 1] = bank as u8;
+        rom[bank * 0x4000 + 2] = (bank >> 8) as u8;
+    }
+    fix_checksums(&mut rom);
+    Cartridge::from_bytes_with_clock(rom, RtcClock::Emulated).unwrap()
+}
+
+#[test]
+fn header_fields() {
+    let rom = rom_image(0x10000, &[(0x0143, 0x80), (0x0146, 0x03), (0x0147, 0x03), (0x0149, 0x03),
+        (0x014A, 0x01), (0x014B, 0x01), (0x014C, 0x02)]);
+    let cartridge = Cartridge::from_bytes(rom).unwrap();
+    let header = cartridge.header().unwrap();
+
+    assert_eq!(header.title, "ORION");
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.cgb_support, CgbSupport::Enhanced);
+    assert!(header.sgb_support);
+    assert_eq!(header.cartridge_type.mbc, Mbc::Mbc1);
+    assert!(header.cartridge_type.ram && header.cartridge_type.battery);
+    assert_eq!(header.rom_size, 0x10000);
+    assert_eq!(header.ram_size, 0x8000);
+    assert_eq!(header.destination, Destination::Overseas);
+    assert_eq!(header.licensee, Licensee::Old(0x01));
+    assert_eq!(header.version, 0x02);
+    assert_eq!(header.to_string(), "ORION (Mbc1, 32 KiB RAM, battery, 64 KiB ROM, version 2)");
+}
+
+#[test]
+fn header_manufacturer_code() {
+    let rom = rom_image(0x8000, &[(0x013F, b'A'), (0x0140, b'B'), (0x0141, b'C'), (0x0142, b'E'),
+        (0x0143, 0xC0), (0x0144, b'0'), (0x0145, b'1'), (0x014B, 0x33)]);
+    let header = Header::parse(&rom).unwrap();
+    assert_eq!(header.title, "ORION");
+    assert_eq!(header.manufacturer_code.as_deref(), Some("ABCE"));
+    assert_eq!(header.cgb_support, CgbSupport::Only);
+    assert_eq!(header.licensee, Licensee::New(String::from("01")));
+    assert_eq!(header.destination, Destination::Japan);
+}
+
+#[test]
+fn header_errors() {
+    let error = |rom: Vec<u8>| Cartridge::from_bytes(rom).err().unwrap();
+    assert!(matches!(error(vec![0; 0x100]), CartridgeError::Truncated { len: 0x100 }));
+    assert!(matches!(error(rom_image(0x8000, &[(0x0147, 0x04)])), CartridgeError::UnknownCartridgeType(0x04)));
+    assert!(matches!(error(rom_image(0x8000, &[(0x0148, 0x09)])), CartridgeError::UnknownRomSize(0x09)));
+    assert!(matches!(error(rom_image(0x8000, &[(0x0149, 0x06)])), CartridgeError::UnknownRamSize(0x06)));
+    assert!(matches!(error(rom_image(0x8000, &[(0x0148, 0x01)])),
+        CartridgeError::RomSizeMismatch { header: 0x10000, actual: 0x8000 }));
+    assert!(matches!(error(rom_image(0x8000, &[(0x0149, 0x02)])),
+        CartridgeError::RamSizeMismatch { cartridge_type: 0x00, ram_size: 0x2000 }));
+
+    let mut rom = rom_image(0x8000, &[]);
+    rom[0x0104] = 0x00;
+    assert!(matches!(error(rom), CartridgeError::LogoMismatch));
+
+    let mut rom = rom_image(0x8000, &[]);
+    rom[0x0134] = b'X';
+    assert!(matches!(error(rom), CartridgeError::HeaderChecksum { .. }));
+
+    // a bad global checksum only warns, as hardware never checks it
+    let mut rom = rom_image(0x8000, &[]);
+    rom[0x0150] = 0x01;
+    let cartridge = Cartridge::from_bytes(rom).unwrap();
+    let expected = cartridge.header().unwrap().global_checksum;
+    assert!(matches!(cartridge.warnings(),
+        [CartridgeError::GlobalChecksum { expected: e, actual: a }] if *e == expected && *a == expected.wrapping_add(1)));
+    assert!(cartridge.warnings()[0].to_string().starts_with("global checksum is"));
+}
+
+#[test]
+fn header_boot_rom_checks() {
+    // with a boot ROM, a bad logo or header checksum is left for it to hang on
+    let mut rom = rom_image(0x8000, &[]);
+    rom[0x0104] = 0x00;
+    rom[0x0134] = b'X';
+    let cartridge = Cartridge::from_bytes_for_boot_rom(rom).unwrap();
+    assert!(matches!(cartridge.warnings(),
+        [CartridgeError::LogoMismatch, CartridgeError::HeaderChecksum { .. }, CartridgeError::GlobalChecksum { .. }]));
+
+    // the image still has to match the header's sizes
+    let rom = rom_image(0x8000, &[(0x0148, 0x01)]);
+    assert!(matches!(Cartridge::from_bytes_for_boot_rom(rom).err().unwrap(), CartridgeError::RomSizeMismatch { .. }));
+    assert!(Cartridge::from_bytes_for_boot_rom(rom_image(0x8000, &[])).unwrap().warnings().is_empty());
+}
@@ -0,0 +1,141 @@
+/// Bytes in the 93LC56, as 128 16-bit words
+pub const EEPROM_LEN: usize = 0x100;
+
+/// 93LC56 serial EEPROM, bit-banged through chip select, clock, data in and data out lines.
+/// Commands are a start bit, a 2-bit opcode and an 8-bit address, shifted in MSB first on rising clock edges.
+pub struct Eeprom {
+    words: Box<[u16]>,
+    write_enable: bool, // cleared at power on, so stray writes can't corrupt the save
+    select: bool,
+    clock: bool,
+    data_out: bool,
+    state: EepromState,
+    written: bool, // contents changed by the current register write
+}
+
+enum EepromState {
+    Idle, // waiting for the start bit
+    Command { bits: u16, len: u8 }, // opcode and address after the start bit
+    Write { address: u8, all: bool, bits: u16, len: u8 },
+    Read { address: u8, word: u16, len: u8 }, // bits left of the word being shifted out
+}
+
+impl Eeprom {
+    pub fn new() -> Eeprom {
+        // an erased chip reads as all ones
+        Eeprom {
+            words: vec![0xFFFF; EEPROM_LEN / 2].into_boxed_slice(),
+            write_enable: false,
+            select: false,
+            clock: false,
+            data_out: true,
+            state: EepromState::Idle,
+            written: false,
+        }
+    }
+
+    /// Register value with chip select in bit 7, clock in bit 6, data in in bit 1 and data out in bit 0
+    pub fn read(&self) -> u8 {
+        ((self.select as u8) << 7) | ((self.clock as u8) << 6) | self.data_out as u8
+    }
+
+    /// Sets the lines from a register write, returning true if the contents changed
+    pub fn write(&mut self, byte: u8) -> bool {
+        let select = byte & 0x80 > 0;
+        let clock = byte & 0x40 > 0;
+        let data_in = byte & 0x02 > 0;
+        let rising = clock && !self.clock;
+        self.clock = clock;
+        self.select = select;
+        if !select {
+            self.state = EepromState::Idle;
+            return false;
+        }
+        if rising { self.shift(data_in) }
+        std::mem::take(&mut self.written)
+    }
+
+    fn shift(&mut self, bit: bool) {
+        self.state = match std::mem::replace(&mut self.state, EepromState::Idle) {
+            EepromState::Idle if bit => EepromState::Command { bits: 0, len: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, len } => {
+                let bits = (bits << 1) | bit as u16;
+                if len + 1 < 10 { EepromState::Command { bits, len: len + 1 } } else { self.command(bits) }
+            }
+            EepromState::Write { address, all, bits, len } => {
+                let bits = (bits << 1) | bit as u16;
+                if len + 1 < 16 { EepromState::Write { address, all, bits, len: len + 1 } } else {
+                    if self.write_enable {
+                        if all { self.words.fill(bits) } else { self.words[address as usize] = bits }
+                        self.written = true;
+                    }
+                    // writes complete immediately, so data out signals ready
+                    self.data_out = true;
+                    EepromState::Idle
+                }
+            }
+            EepromState::Read { address, word, len } => {
+                self.data_out = word & 0x8000 > 0;
+                if len > 1 {
+                    EepromState::Read { address, word: word << 1, len: len - 1 }
+                } else {
+                    // reads continue into the next word
+                    let address = (address + 1) & 0x7F;
+                    EepromState::Read { address, word: self.words[address as usize], len: 16 }
+                }
+            }
+        };
+    }
+
+    /// Runs the 2-bit opcode and 8-bit address shifted in after the start bit
+    fn command(&mut self, bits: u16) -> EepromState {
+        // the 93LC56 only decodes 7 address bits in 16-bit mode
+        let address = (bits & 0x7F) as u8;
+        match (bits >> 8, (bits >> 6) & 0x03) {
+            (0b10, _) => {
+                // a dummy 0 precedes the data
+                self.data_out = false;
+                EepromState::Read { address, word: self.words[address as usize], len: 16 }
+            }
+            (0b01, _) => EepromState::Write { address, all: false, bits: 0, len: 0 },
+            (0b11, _) => {
+                if self.write_enable {
+                    self.words[address as usize] = 0xFFFF;
+                    self.written = true;
+                }
+                self.data_out = true;
+                EepromState::Idle
+            }
+            (_, 0b11) => {
+                self.write_enable = true;
+                EepromState::Idle
+            }
+            (_, 0b00) => {
+                self.write_enable = false;
+                EepromState::Idle
+            }
+            (_, 0b10) => {
+                if self.write_enable {
+                    self.words.fill(0xFFFF);
+                    self.written = true;
+                }
+                self.data_out = true;
+                EepromState::Idle
+            }
+            // WRAL, writing one word everywhere
+            _ => EepromState::Write { address: 0, all: true, bits: 0, len: 0 },
+        }
+    }
+
+    /// Contents for a save file, with each word stored low byte first
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    pub fn load_bytes(&mut self, bytes: &[u8]) {
+        for (word, bytes) in self.words.iter_mut().zip(bytes.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+use crate::system::cartridge::CartridgeError;
+
+/// Logo at 0x0104-0x0133, compared against the copy in the boot ROM
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Memory bank controller, or other mapper, on the cartridge
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mbc {
+    None,
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    Mmm01,
+    PocketCamera,
+    BandaiTama5,
+    HuC1,
+    HuC3,
+}
+
+/// Hardware on the cartridge, from the type code at 0x0147
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CartridgeType {
+    pub code: u8,
+    pub mbc: Mbc,
+    pub ram: bool, // external RAM, sized by the RAM size code
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+impl CartridgeType {
+    pub fn from_code(code: u8) -> Option<CartridgeType> {
+        // (controller, RAM, battery, timer, rumble)
+        let (mbc, ram, battery, timer, rumble) = match code {
+            0x00 => (Mbc::None, false, false, false, false),
+            0x01 => (Mbc::Mbc1, false, false, false, false),
+            0x02 => (Mbc::Mbc1, true, false, false, false),
+            0x03 => (Mbc::Mbc1, true, true, false, false),
+            0x05 => (Mbc::Mbc2, false, false, false, false),
+            0x06 => (Mbc::Mbc2, false, true, false, false),
+            0x08 => (Mbc::None, true, false, false, false),
+            0x09 => (Mbc::None, true, true, false, false),
+            0x0B => (Mbc::Mmm01, false, false, false, false),
+            0x0C => (Mbc::Mmm01, true, false, false, false),
+            0x0D => (Mbc::Mmm01, true, true, false, false),
+            0x0F => (Mbc::Mbc3, false, true, true, false),
+            0x10 => (Mbc::Mbc3, true, true, true, false),
+            0x11 => (Mbc::Mbc3, false, false, false, false),
+            0x12 => (Mbc::Mbc3, true, false, false, false),
+            0x13 => (Mbc::Mbc3, true, true, false, false),
+            0x19 => (Mbc::Mbc5, false, false, false, false),
+            0x1A => (Mbc::Mbc5, true, false, false, false),
+            0x1B => (Mbc::Mbc5, true, true, false, false),
+            0x1C => (Mbc::Mbc5, false, false, false, true),
+            0x1D => (Mbc::Mbc5, true, false, false, true),
+            0x1E => (Mbc::Mbc5, true, true, false, true),
+            0x20 => (Mbc::Mbc6, true, true, false, false),
+            0x22 => (Mbc::Mbc7, false, true, false, true), // accelerometer and EEPROM
+            0xFC => (Mbc::PocketCamera, true, true, false, false),
+            0xFD => (Mbc::BandaiTama5, false, true, true, false),
+            0xFE => (Mbc::HuC3, true, true, true, false),
+            0xFF => (Mbc::HuC1, true, true, false, false),
+            _ => return None,
+        };
+        Some(CartridgeType { code, mbc, ram, battery, timer, rumble })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CgbSupport {
+    None,
+    Enhanced, // runs on DMG too
+    Only,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Licensee {
+    Old(u8),
+    New(String), // two ASCII characters, used when the old code is 0x33
+}
+
+/// Cartridge header at 0x0100-0x014F
+#[derive(Clone, PartialEq, Debug)]
+pub struct Header {
+    pub title: String,
+    pub manufacturer_code: Option<String>, // only on later cartridges, taking the end of the title
+    pub cgb_support: CgbSupport,
+    pub sgb_support: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size: usize, // bytes
+    pub ram_size: usize, // bytes
+    pub destination: Destination,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    /// Parses the header fields, without checking them against the rest of the image
+    pub fn parse(rom: &[u8]) -> Result<Header, CartridgeError> {
+        if rom.len() < 0x150 { return Err(CartridgeError::Truncated { len: rom.len() }) }
+
+        let cgb_support = match rom[0x0143] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Enhanced,
+            _ => CgbSupport::None,
+        };
+        let licensee = match rom[0x014B] {
+            0x33 => Licensee::New(ascii(&rom[0x0144..0x0146])),
+            code => Licensee::Old(code),
+        };
+        // color cartridges may end the title with a 4 character uppercase manufacturer code
+        let code_bytes = &rom[0x013F..0x0143];
+        let has_manufacturer_code = cgb_support != CgbSupport::None && matches!(licensee, Licensee::New(_))
+            && code_bytes.iter().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit());
+        let (title, manufacturer_code) = match (has_manufacturer_code, cgb_support) {
+            (true, _) => (ascii(&rom[0x0134..0x013F]), Some(ascii(code_bytes))),
+            (false, CgbSupport::None) => (ascii(&rom[0x0134..0x0144]), None),
+            (false, _) => (ascii(&rom[0x0134..0x0143]), None),
+        };
+
+        let cartridge_type = CartridgeType::from_code(rom[0x0147])
+            .ok_or(CartridgeError::UnknownCartridgeType(rom[0x0147]))?;
+        let rom_size = match rom[0x0148] {
+            code @ 0x00..=0x08 => 0x8000 << code,
+            0x52 => 72 * 0x4000,
+            0x53 => 80 * 0x4000,
+            0x54 => 96 * 0x4000,
+            code => return Err(CartridgeError::UnknownRomSize(code)),
+        };
+        let ram_size = match rom[0x0149] {
+            0x00 => 0,
+            0x01 => 0x800, // unofficial
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            code => return Err(CartridgeError::UnknownRamSize(code)),
+        };
+        let destination = if rom[0x014A] == 0x00 { Destination::Japan } else { Destination::Overseas };
+
+        Ok(Header {
+            title,
+            manufacturer_code,
+            cgb_support,
+            sgb_support: rom[0x0146] == 0x03,
+            cartridge_type,
+            rom_size,
+            ram_size,
+            destination,
+            licensee,
+            version: rom[0x014C],
+            header_checksum: rom[0x014D],
+            global_checksum: ((rom[0x014E] as u16) << 8) | rom[0x014F] as u16,
+        })
+    }
+
+    /// Checks the image as the boot ROM would, plus the consistency of the header with the image,
+    /// returning the mismatches that still allow the cartridge to run as warnings.
+    /// Hardware never checks the global checksum, and when a boot ROM is run,
+    /// its own logo and header checksum checks decide whether the cartridge starts.
+    pub fn validate(&self, rom: &[u8], boot_rom: bool) -> Result<Vec<CartridgeError>, CartridgeError> {
+        let mut warnings = Vec::new();
+        if rom[0x0104..0x0134] != NINTENDO_LOGO { warnings.push(CartridgeError::LogoMismatch) }
+        let header_checksum = header_checksum(rom);
+        if header_checksum != self.header_checksum {
+            warnings.push(CartridgeError::HeaderChecksum { expected: self.header_checksum, actual: header_checksum });
+        }
+        if !boot_rom && !warnings.is_empty() { return Err(warnings.remove(0)) }
+        if rom.len() != self.rom_size {
+            return Err(CartridgeError::RomSizeMismatch { header: self.rom_size, actual: rom.len() });
+        }
+        if self.ram_size > 0 && !self.cartridge_type.ram {
+            return Err(CartridgeError::RamSizeMismatch { cartridge_type: self.cartridge_type.code, ram_size: self.ram_size });
+        }
+        let global_checksum = global_checksum(rom);
+        if global_checksum != self.global_checksum {
+            warnings.push(CartridgeError::GlobalChecksum { expected: self.global_checksum, actual: global_checksum });
+        }
+        Ok(warnings)
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?}", self.title, self.cartridge_type.mbc)?;
+        if self.ram_size > 0 { write!(f, ", {} KiB RAM", self.ram_size / 1024)? }
+        if self.cartridge_type.battery { write!(f, ", battery")? }
+        if self.cartridge_type.timer { write!(f, ", timer")? }
+        if self.cartridge_type.rumble { write!(f, ", rumble")? }
+        write!(f, ", {} KiB ROM, version {})", self.rom_size / 1024, self.version)
+    }
+}
+
+/// Checksum of 0x0134-0x014C, stored at 0x014D
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[0x0134..=0x014C].iter().fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1))
+}
+
+/// Sum of every byte in the image besides the checksum itself, stored at 0x014E-0x014F
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    let sum = rom.iter().fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+    sum.wrapping_sub(rom[0x014E] as u16).wrapping_sub(rom[0x014F] as u16)
+}
+
+fn ascii(bytes: &[u8]) -> String {
+    // padded with zeroes
+    bytes.iter().take_while(|byte| **byte != 0).map(|byte| *byte as char).collect()
+}
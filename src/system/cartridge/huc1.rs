@@ -0,0 +1,53 @@
+use crate::system::cartridge::infrared::Infrared;
+
+/// HuC1 controller, with up to 1 MiB ROM, 32 KiB RAM,
+/// and an infrared LED and receiver that can be mapped in place of RAM
+pub struct HuC1 {
+    ir_select: bool, // 0xA000-0xBFFF maps the infrared port instead of RAM
+    rom_bank: u8, // 6 bits
+    ram_bank: u8, // 2 bits
+    pub infrared: Infrared,
+}
+
+impl HuC1 {
+    pub fn new() -> HuC1 {
+        HuC1 { ir_select: false, rom_bank: 1, ram_bank: 0, infrared: Infrared::new() }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ir_select = byte & 0x0F == 0x0E,
+            // bank 0 can't be selected here, so it's replaced by 1
+            0x2000..=0x3FFF => self.rom_bank = if byte & 0x3F == 0 { 1 } else { byte & 0x3F },
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x03,
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF. RAM has no enable, and is always mapped unless IR is.
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.ir_select { return None }
+        Some((self.ram_bank as usize * 0x2000) | (addr as usize & 0x1FFF))
+    }
+
+    pub fn read_ir(&self) -> Option<u8> {
+        if !self.ir_select { return None }
+        Some(self.infrared.read())
+    }
+
+    /// Returns false if the write should go to RAM instead
+    pub fn write_ir(&mut self, byte: u8) -> bool {
+        if !self.ir_select { return false }
+        self.infrared.write(byte);
+        true
+    }
+}
@@ -0,0 +1,44 @@
+use super::cartridge_tests::banked_cartridge;
+use super::infrared::InfraredLink;
+
+#[test]
+fn huc1_banks() {
+    let mut cartridge = banked_cartridge(0xFF, 0x100000);
+    cartridge.write_rom(0x2000, 0x3F);
+    assert_eq!(cartridge.read_rom(0x4001), 63);
+    cartridge.write_rom(0x2000, 0x40); // bank 0 replaced by 1, checking 6 bits
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+
+    // RAM is mapped without being enabled
+    cartridge.write_rom(0x4000, 0x02);
+    cartridge.write_ram(0xA000, 0x22);
+    cartridge.write_rom(0x4000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0x00);
+    cartridge.write_rom(0x4000, 0x02);
+    assert_eq!(cartridge.read_ram(0xA000), 0x22);
+}
+
+#[test]
+fn huc1_infrared() {
+    let mut sender = banked_cartridge(0xFF, 0x100000);
+    let mut receiver = banked_cartridge(0xFF, 0x100000);
+    assert!(sender.has_infrared());
+    let (a, b) = InfraredLink::pair();
+    sender.connect_infrared(Box::new(a));
+    receiver.connect_infrared(Box::new(b));
+
+    receiver.write_ram(0xA000, 0x55);
+    receiver.write_rom(0x0000, 0x0E);
+    sender.write_rom(0x0000, 0x0E);
+    assert_eq!(receiver.read_ram(0xA000), 0xC0);
+    sender.write_ram(0xA000, 0x01);
+    assert_eq!(receiver.read_ram(0xA000), 0xC1);
+    sender.write_ram(0xA000, 0x00);
+    assert_eq!(receiver.read_ram(0xA000), 0xC0);
+
+    // switching back maps RAM, untouched by the IR writes
+    sender.write_rom(0x0000, 0x00);
+    receiver.write_rom(0x0000, 0x00);
+    assert_eq!(sender.read_ram(0xA000), 0x00);
+    assert_eq!(receiver.read_ram(0xA000), 0x55);
+}
@@ -0,0 +1,227 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::system::cartridge::infrared::Infrared;
+use crate::system::cartridge::rtc::{RtcClock, CYCLES_PER_SECOND};
+
+/// HuC3 controller, with up to 2 MiB ROM, 32 KiB RAM, an infrared port,
+/// and a clock driven through a command/response protocol in place of RAM
+pub struct HuC3 {
+    mode: u8, // what 0xA000-0xBFFF maps, from the 0x0000-0x1FFF register
+    rom_bank: u8, // 7 bits
+    ram_bank: u8, // 2 bits
+    command: u8, // command in the upper nibble and its argument in the lower, as last written in mode 0x0B
+    response: u8, // command in the upper nibble and its result in the lower, read in mode 0x0C
+    address: u8, // into the clock's nibble memory
+    memory: Box<[u8]>, // 0x100 entries of 4 bits each, with the time copied to and from 0x00-0x05
+    pub clock: HuC3Clock,
+    pub infrared: Infrared,
+}
+
+/// HuC3 clock, counting minutes into the day and a 12-bit day counter
+pub struct HuC3Clock {
+    minutes: u16,
+    days: u16,
+    clock: RtcClock,
+    cycles: u32, // cycles into the current minute, when emulated
+    synced: SystemTime, // time the counters were last brought up to date, on the wall clock
+}
+
+const MINUTES_PER_DAY: u16 = 1440;
+
+/// Length of the clock state appended to save files: minutes and days as little-endian u32s,
+/// a u64 UNIX timestamp, then the clock's nibble memory one nibble per byte
+pub const HUC3_TRAILER_LEN: usize = 16 + 0x100;
+
+impl HuC3 {
+    pub fn new(clock: RtcClock) -> HuC3 {
+        HuC3 {
+            mode: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            command: 0,
+            response: 0,
+            address: 0,
+            memory: vec![0; 0x100].into_boxed_slice(),
+            clock: HuC3Clock::new(clock),
+            infrared: Infrared::new(),
+        }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.mode = byte & 0x0F,
+            // unlike MBC1-3, bank 0 can be mapped to 0x4000-0x7FFF
+            0x2000..=0x3FFF => self.rom_bank = byte & 0x7F,
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x03,
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF, or None while another mode is selected.
+    /// Mode 0x00 maps RAM read-only, which write_mapped enforces.
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.mode != 0x00 && self.mode != 0x0A { return None }
+        Some((self.ram_bank as usize * 0x2000) | (addr as usize & 0x1FFF))
+    }
+
+    /// Register selected in place of RAM, if any
+    pub fn read_mapped(&self) -> Option<u8> {
+        match self.mode {
+            0x00 | 0x0A => None,
+            0x0C => Some(self.response),
+            0x0D => Some(0xFF), // commands complete immediately, so the semaphore always reads ready
+            0x0E => Some(self.infrared.read()),
+            _ => Some(0xFF),
+        }
+    }
+
+    /// Returns None if the write should go to RAM instead,
+    /// or whether the clock or its memory changed and needs saving
+    pub fn write_mapped(&mut self, byte: u8) -> Option<bool> {
+        match self.mode {
+            0x0A => return None,
+            0x0B => self.command = byte & 0x7F,
+            // clearing bit 0 of the semaphore runs the last command
+            0x0D if byte & 0x01 == 0 => return Some(self.execute()),
+            0x0E => self.infrared.write(byte),
+            _ => {}
+        }
+        Some(false)
+    }
+
+    pub fn to_trailer(&mut self) -> [u8; HUC3_TRAILER_LEN] {
+        let (minutes, days) = self.clock.time();
+        let mut trailer = [0; HUC3_TRAILER_LEN];
+        trailer[0..4].copy_from_slice(&(minutes as u32).to_le_bytes());
+        trailer[4..8].copy_from_slice(&(days as u32).to_le_bytes());
+        let timestamp = match self.clock.clock {
+            RtcClock::WallClock => self.clock.synced,
+            RtcClock::Emulated => SystemTime::now(),
+        };
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).map_or(0, |timestamp| timestamp.as_secs());
+        trailer[8..16].copy_from_slice(&timestamp.to_le_bytes());
+        trailer[16..].copy_from_slice(&self.memory);
+        trailer
+    }
+
+    /// Restores clock state from a save file. On the wall clock, the
+    /// time since the save was written is added to the counters.
+    pub fn load_trailer(&mut self, trailer: &[u8; HUC3_TRAILER_LEN]) {
+        let minutes = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+        let days = u32::from_le_bytes(trailer[4..8].try_into().unwrap());
+        self.clock.set_time(minutes as u16, days as u16);
+        for (nibble, byte) in self.memory.iter_mut().zip(&trailer[16..]) { *nibble = byte & 0x0F }
+
+        let timestamp = u64::from_le_bytes(trailer[8..16].try_into().unwrap());
+        if self.clock.clock == RtcClock::WallClock {
+            self.clock.synced = UNIX_EPOCH + Duration::from_secs(timestamp);
+            self.clock.sync();
+        }
+    }
+
+    /// Returns true if the command wrote the clock's memory or set the clock
+    fn execute(&mut self) -> bool {
+        let argument = self.command & 0x0F;
+        let changed = self.command >> 4 == 0x3 || self.command == 0x61;
+        let result = match self.command >> 4 {
+            0x1 => {
+                let value = self.memory[self.address as usize];
+                self.address = self.address.wrapping_add(1);
+                value
+            }
+            0x3 => {
+                self.memory[self.address as usize] = argument;
+                self.address = self.address.wrapping_add(1);
+                argument
+            }
+            0x4 => {
+                self.address = (self.address & 0xF0) | argument;
+                argument
+            }
+            0x5 => {
+                self.address = (self.address & 0x0F) | (argument << 4);
+                argument
+            }
+            0x6 => match argument {
+                0x0 => {
+                    let (minutes, days) = self.clock.time();
+                    for i in 0..3 {
+                        self.memory[i] = ((minutes >> (i * 4)) & 0x0F) as u8;
+                        self.memory[i + 3] = ((days >> (i * 4)) & 0x0F) as u8;
+                    }
+                    0x0
+                }
+                0x1 => {
+                    let nibbles = |start: usize| (0..3).fold(0, |value, i| value | ((self.memory[start + i] as u16) << (i * 4)));
+                    self.clock.set_time(nibbles(0), nibbles(3));
+                    0x0
+                }
+                0x2 => 0x1, // status, always ready
+                _ => 0x0, // the tone generator has no sound output to drive
+            },
+            _ => 0x0,
+        };
+        self.response = (self.command & 0x70) | (result & 0x0F);
+        changed
+    }
+}
+
+impl HuC3Clock {
+    pub fn new(clock: RtcClock) -> HuC3Clock {
+        HuC3Clock { minutes: 0, days: 0, clock, cycles: 0, synced: SystemTime::now() }
+    }
+
+    pub fn set_clock(&mut self, clock: RtcClock) {
+        self.sync();
+        self.clock = clock;
+        self.synced = SystemTime::now();
+    }
+
+    pub fn update_cycle(&mut self, cycles: u8) {
+        if self.clock != RtcClock::Emulated { return }
+        self.cycles += cycles as u32;
+        if self.cycles >= 60 * CYCLES_PER_SECOND {
+            self.cycles -= 60 * CYCLES_PER_SECOND;
+            self.advance(1);
+        }
+    }
+
+    /// Minutes into the day and the day counter
+    pub fn time(&mut self) -> (u16, u16) {
+        self.sync();
+        (self.minutes, self.days)
+    }
+
+    pub fn set_time(&mut self, minutes: u16, days: u16) {
+        self.sync();
+        self.minutes = minutes % MINUTES_PER_DAY;
+        self.days = days & 0x0FFF;
+        self.cycles = 0;
+    }
+
+    /// Brings the counters up to date with the wall clock
+    fn sync(&mut self) {
+        if self.clock != RtcClock::WallClock { return }
+        // time going backwards on the host is ignored
+        let elapsed = SystemTime::now().duration_since(self.synced).map_or(0, |elapsed| elapsed.as_secs() / 60);
+        self.advance(elapsed);
+        // fractions of a minute are kept for the next sync
+        self.synced += Duration::from_secs(elapsed * 60);
+    }
+
+    /// Advances the counters by the given number of minutes
+    pub fn advance(&mut self, minutes: u64) {
+        let total = minutes + self.minutes as u64 + MINUTES_PER_DAY as u64 * self.days as u64;
+        self.minutes = (total % MINUTES_PER_DAY as u64) as u16;
+        self.days = (total / MINUTES_PER_DAY as u64 % 0x1000) as u16;
+    }
+}
@@ -0,0 +1,115 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use super::Cartridge;
+use super::cartridge_tests::banked_cartridge;
+use super::infrared::InfraredPort;
+use super::rtc::CYCLES_PER_SECOND;
+
+/// Sends a command through mode 0x0B, runs it through the semaphore, and reads the response in mode 0x0C
+fn command(cartridge: &mut Cartridge, command: u8) -> u8 {
+    cartridge.write_rom(0x0000, 0x0B);
+    cartridge.write_ram(0xA000, command);
+    cartridge.write_rom(0x0000, 0x0D);
+    cartridge.write_ram(0xA000, 0xFE);
+    assert_eq!(cartridge.read_ram(0xA000) & 0x01, 0x01);
+    cartridge.write_rom(0x0000, 0x0C);
+    cartridge.read_ram(0xA000)
+}
+
+/// Minutes and days, read through the clock's memory
+fn read_time(cartridge: &mut Cartridge) -> (u16, u16) {
+    command(cartridge, 0x60);
+    command(cartridge, 0x40);
+    command(cartridge, 0x50);
+    let nibbles: Vec<u16> = (0..6).map(|_| (command(cartridge, 0x10) & 0x0F) as u16).collect();
+    (nibbles[0] | nibbles[1] << 4 | nibbles[2] << 8, nibbles[3] | nibbles[4] << 4 | nibbles[5] << 8)
+}
+
+/// Test stub that records LED writes and shines a light set by the test
+struct StubPort {
+    led: Rc<Cell<bool>>,
+    light: Rc<Cell<bool>>,
+}
+
+impl InfraredPort for StubPort {
+    fn set_led(&mut self, on: bool) {
+        self.led.set(on);
+    }
+
+    fn receiving(&self) -> bool {
+        self.light.get()
+    }
+}
+
+#[test]
+fn huc3_banks() {
+    let mut cartridge = banked_cartridge(0xFE, 0x200000);
+    cartridge.write_rom(0x2000, 0x7F);
+    assert_eq!(cartridge.read_rom(0x4001), 127);
+    cartridge.write_rom(0x2000, 0x00); // bank 0 can be mapped
+    assert_eq!(cartridge.read_rom(0x4001), 0);
+
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x03);
+    cartridge.write_ram(0xA000, 0x33);
+    cartridge.write_rom(0x4000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0x00);
+
+    // mode 0x00 maps RAM read-only
+    cartridge.write_rom(0x4000, 0x03);
+    cartridge.write_rom(0x0000, 0x00);
+    cartridge.write_ram(0xA000, 0x44);
+    assert_eq!(cartridge.read_ram(0xA000), 0x33);
+}
+
+#[test]
+fn huc3_rtc() {
+    let mut cartridge = banked_cartridge(0xFE, 0x200000);
+    assert_eq!(command(&mut cartridge, 0x62), 0x61); // status
+
+    // write 1439 minutes (0x59F) and 0xABC days to memory 00-05, then set the clock from it
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x50);
+    for nibble in [0xF, 0x9, 0x5, 0xC, 0xB, 0xA] {
+        assert_eq!(command(&mut cartridge, 0x30 | nibble), 0x30 | nibble);
+    }
+    command(&mut cartridge, 0x61);
+    assert_eq!(read_time(&mut cartridge), (1439, 0xABC));
+
+    for _ in 0..60 * (CYCLES_PER_SECOND / 0x80) { cartridge.update_cycle(0x80) }
+    assert_eq!(read_time(&mut cartridge), (0, 0xABD));
+}
+
+#[test]
+fn huc3_ram_written() {
+    let mut cartridge = banked_cartridge(0xFE, 0x200000);
+    // moving the address and reading leave nothing new to save
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x10);
+    assert!(!cartridge.ram_written());
+
+    // writing the clock's memory or setting the clock does
+    command(&mut cartridge, 0x35);
+    assert!(cartridge.ram_written());
+    cartridge.save_data();
+    command(&mut cartridge, 0x61);
+    assert!(cartridge.ram_written());
+}
+
+#[test]
+fn huc3_infrared() {
+    let mut cartridge = banked_cartridge(0xFE, 0x200000);
+    let led = Rc::new(Cell::new(false));
+    let light = Rc::new(Cell::new(false));
+    cartridge.connect_infrared(Box::new(StubPort { led: led.clone(), light: light.clone() }));
+
+    cartridge.write_rom(0x0000, 0x0E);
+    assert_eq!(cartridge.read_ram(0xA000), 0xC0);
+    light.set(true);
+    assert_eq!(cartridge.read_ram(0xA000), 0xC1);
+    cartridge.write_ram(0xA000, 0x01);
+    assert!(led.get());
+    cartridge.write_ram(0xA000, 0x00);
+    assert!(!led.get());
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Endpoint for a cartridge's infrared LED and receiver
+pub trait InfraredPort {
+    /// Called when the cartridge switches its LED on or off
+    fn set_led(&mut self, on: bool);
+    /// True while light is reaching the cartridge's receiver
+    fn receiving(&self) -> bool;
+}
+
+/// One end of an infrared link between two cartridges, where each LED lights the other's receiver.
+/// Both ends can be moved to separate threads.
+pub struct InfraredLink {
+    led: Arc<AtomicBool>,
+    receiver: Arc<AtomicBool>,
+}
+
+impl InfraredLink {
+    pub fn pair() -> (InfraredLink, InfraredLink) {
+        let a = Arc::new(AtomicBool::new(false));
+        let b = Arc::new(AtomicBool::new(false));
+        (InfraredLink { led: a.clone(), receiver: b.clone() }, InfraredLink { led: b, receiver: a })
+    }
+}
+
+impl InfraredPort for InfraredLink {
+    fn set_led(&mut self, on: bool) {
+        self.led.store(on, Ordering::Relaxed);
+    }
+
+    fn receiving(&self) -> bool {
+        self.receiver.load(Ordering::Relaxed)
+    }
+}
+
+/// Infrared port of a cartridge, which sees no light while nothing is connected
+pub struct Infrared {
+    port: Option<Box<dyn InfraredPort>>,
+}
+
+impl Infrared {
+    pub fn new() -> Infrared {
+        Infrared { port: None }
+    }
+
+    pub fn connect(&mut self, port: Box<dyn InfraredPort>) {
+        self.port = Some(port);
+    }
+
+    /// Register value seen by the CPU, with bit 0 set while receiving light
+    pub fn read(&self) -> u8 {
+        0xC0 | self.port.as_ref().is_some_and(|port| port.receiving()) as u8
+    }
+
+    /// Bit 0 switches the LED
+    pub fn write(&mut self, byte: u8) {
+        if let Some(port) = &mut self.port { port.set_led(byte & 0x01 > 0) }
+    }
+}
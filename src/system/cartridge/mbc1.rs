@@ -0,0 +1,50 @@
+/// MBC1 controller, with up to 2 MiB ROM and 32 KiB RAM.
+/// MBC1M multicarts wire the upper register one bit lower, giving 4 games of 256 KiB.
+pub struct Mbc1 {
+    ram_enable: bool,
+    bank1: u8, // 5 bits, lower bits of the ROM bank
+    bank2: u8, // 2 bits, upper bits of the ROM bank, or the RAM bank
+    mode: bool, // bank2 also applies to 0x0000-0x3FFF and RAM
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(multicart: bool) -> Mbc1 {
+        Mbc1 { ram_enable: false, bank1: 1, bank2: 0, mode: false, multicart }
+    }
+
+    /// Multicarts are 1 MiB, with a game header at the start of each 256 KiB
+    pub fn detect_multicart(rom: &[u8]) -> bool {
+        use crate::system::cartridge::header::NINTENDO_LOGO;
+        rom.len() == 0x100000 && rom[0x40104..0x40134] == NINTENDO_LOGO
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enable = byte & 0x0F == 0x0A,
+            // bank 0 can't be selected here, so it's replaced by 1
+            0x2000..=0x3FFF => self.bank1 = if byte & 0x1F == 0 { 1 } else { byte & 0x1F },
+            0x4000..=0x5FFF => self.bank2 = byte & 0x03,
+            0x6000..=0x7FFF => self.mode = byte & 0x01 > 0,
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let (bank1, shift) = if self.multicart { (self.bank1 & 0x0F, 4) } else { (self.bank1, 5) };
+        let bank = match addr {
+            0x0000..=0x3FFF if self.mode => self.bank2 << shift,
+            0x0000..=0x3FFF => 0,
+            _ => (self.bank2 << shift) | bank1,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF, or None while RAM is disabled
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enable { return None }
+        let bank = if self.mode { self.bank2 } else { 0 };
+        Some((bank as usize * 0x2000) | (addr as usize & 0x1FFF))
+    }
+}
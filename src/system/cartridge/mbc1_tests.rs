@@ -0,0 +1,87 @@
+use super::Cartridge;
+use super::cartridge_tests::{banked_cartridge, fix_checksums, rom_image};
+use super::header::NINTENDO_LOGO;
+
+#[test]
+fn mbc1_rom_bank() {
+    let mut cartridge = banked_cartridge(0x01, 0x80000); // 32 banks
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+
+    cartridge.write_rom(0x2000, 0x05);
+    assert_eq!(cartridge.read_rom(0x4001), 5);
+    assert_eq!(cartridge.read_rom(0x0001), 0); // bank 0 is fixed
+
+    // bank 0 is replaced by bank 1, checking all 5 bits of the register
+    cartridge.write_rom(0x3FFF, 0x00);
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+    cartridge.write_rom(0x2000, 0x20);
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+
+    // bank numbers past the end of the ROM wrap around
+    cartridge.write_rom(0x2000, 0x1F);
+    assert_eq!(cartridge.read_rom(0x4001), 31);
+    let mut cartridge = banked_cartridge(0x01, 0x20000); // 8 banks
+    cartridge.write_rom(0x2000, 0x09);
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+}
+
+#[test]
+fn mbc1_upper_bank() {
+    let mut cartridge = banked_cartridge(0x01, 0x200000); // 128 banks
+    cartridge.write_rom(0x2000, 0x02);
+    cartridge.write_rom(0x4000, 0x03);
+    assert_eq!(cartridge.read_rom(0x4001), 0x62);
+    assert_eq!(cartridge.read_rom(0x0001), 0x00);
+
+    // banks 0x20, 0x40 and 0x60 can only be reached at 0x0000 in mode 1
+    cartridge.write_rom(0x2000, 0x00);
+    assert_eq!(cartridge.read_rom(0x4001), 0x61);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_rom(0x0001), 0x60);
+    assert_eq!(cartridge.read_rom(0x4001), 0x61);
+}
+
+#[test]
+fn mbc1_ram() {
+    let mut cartridge = banked_cartridge(0x03, 0x80000); // 32 KiB RAM
+    // disabled until 0x0A is written to 0x0000-0x1FFF
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+
+    // RAM banks are only switched in mode 1
+    cartridge.write_rom(0x4000, 0x02);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_ram(0xA000), 0x00);
+    cartridge.write_ram(0xBFFF, 0x34);
+    cartridge.write_rom(0x4000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+    assert_eq!(cartridge.read_ram(0xBFFF), 0x00);
+    cartridge.write_rom(0x4000, 0x02);
+    assert_eq!(cartridge.read_ram(0xBFFF), 0x34);
+
+    cartridge.write_rom(0x1FFF, 0x00);
+    assert_eq!(cartridge.read_ram(0xBFFF), 0xFF);
+}
+
+#[test]
+fn mbc1_multicart() {
+    let mut rom = rom_image(0x100000, &[(0x0147, 0x01)]);
+    for bank in 1..64 { rom[bank * 0x4000] = bank as u8 }
+    rom[0x40104..0x40134].copy_from_slice(&NINTENDO_LOGO); // second game's header
+    fix_checksums(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(rom).unwrap();
+
+    // upper register selects the game, with only 4 bits of the lower one used
+    cartridge.write_rom(0x4000, 0x01);
+    cartridge.write_rom(0x2000, 0x12);
+    assert_eq!(cartridge.read_rom(0x4000), 0x12);
+    cartridge.write_rom(0x2000, 0x10); // zero check still covers 5 bits
+    assert_eq!(cartridge.read_rom(0x4000), 0x10);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_rom(0x0104), NINTENDO_LOGO[0]);
+    assert_eq!(cartridge.read_rom(0x0000), 0x10);
+}
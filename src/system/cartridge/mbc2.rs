@@ -0,0 +1,39 @@
+/// MBC2 controller, with up to 256 KiB ROM and 512 half-bytes of built-in RAM
+pub struct Mbc2 {
+    ram_enable: bool,
+    rom_bank: u8, // 4 bits
+}
+
+/// Built-in RAM, mirrored across 0xA000-0xBFFF
+pub const MBC2_RAM_LEN: usize = 0x200;
+
+impl Mbc2 {
+    pub fn new() -> Mbc2 {
+        Mbc2 { ram_enable: false, rom_bank: 1 }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        // both registers share 0x0000-0x3FFF, selected by bit 8 of the address
+        match addr {
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enable = byte & 0x0F == 0x0A,
+            // bank 0 can't be selected here, so it's replaced by 1
+            0x0000..=0x3FFF => self.rom_bank = if byte & 0x0F == 0 { 1 } else { byte & 0x0F },
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF, or None while RAM is disabled
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enable { return None }
+        Some(addr as usize & (MBC2_RAM_LEN - 1))
+    }
+}
@@ -0,0 +1,39 @@
+use super::cartridge_tests::banked_cartridge;
+
+#[test]
+fn mbc2_rom_bank() {
+    let mut cartridge = banked_cartridge(0x06, 0x40000);
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+
+    // bit 8 of the address selects the ROM bank register
+    cartridge.write_rom(0x2100, 0x0F);
+    assert_eq!(cartridge.read_rom(0x4001), 15);
+    cartridge.write_rom(0x0100, 0x13); // upper bits ignored
+    assert_eq!(cartridge.read_rom(0x4001), 3);
+    cartridge.write_rom(0x3F00, 0x00); // bank 0 replaced by 1
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+    cartridge.write_rom(0x2000, 0x05); // bit 8 clear, RAM enable
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+    assert_eq!(cartridge.read_rom(0x0001), 0);
+}
+
+#[test]
+fn mbc2_ram() {
+    let mut cartridge = banked_cartridge(0x06, 0x40000);
+    cartridge.write_ram(0xA000, 0x05);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+
+    cartridge.write_rom(0x0100, 0x0A); // bit 8 set, ROM bank
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+    cartridge.write_rom(0x3E00, 0x0A);
+
+    // half-bytes read back with the upper nibble set, mirrored every 512 bytes
+    cartridge.write_ram(0xA000, 0x35);
+    assert_eq!(cartridge.read_ram(0xA000), 0xF5);
+    assert_eq!(cartridge.read_ram(0xA200), 0xF5);
+    cartridge.write_ram(0xBFFF, 0x0C);
+    assert_eq!(cartridge.read_ram(0xA1FF), 0xFC);
+
+    cartridge.write_rom(0x0000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
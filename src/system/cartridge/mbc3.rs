@@ -0,0 +1,68 @@
+use crate::system::cartridge::rtc::{Rtc, RtcClock};
+
+/// MBC3 controller, with up to 2 MiB ROM, 32 KiB RAM and an optional real-time clock
+pub struct Mbc3 {
+    ram_enable: bool, // also enables the clock registers
+    rom_bank: u8, // 7 bits
+    ram_select: u8, // RAM bank 00-03, or clock register 08-0C
+    latch_armed: bool, // 0x00 was written to the latch register, so 0x01 latches the clock
+    pub rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(timer: bool, clock: RtcClock) -> Mbc3 {
+        let rtc = if timer { Some(Rtc::new(clock)) } else { None };
+        Mbc3 { ram_enable: false, rom_bank: 1, ram_select: 0, latch_armed: false, rtc }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enable = byte & 0x0F == 0x0A,
+            // bank 0 can't be selected here, so it's replaced by 1
+            0x2000..=0x3FFF => self.rom_bank = if byte & 0x7F == 0 { 1 } else { byte & 0x7F },
+            0x4000..=0x5FFF => self.ram_select = byte & 0x0F,
+            0x6000..=0x7FFF => {
+                if self.latch_armed && byte == 0x01 {
+                    if let Some(rtc) = &mut self.rtc { rtc.latch() }
+                }
+                self.latch_armed = byte == 0x00;
+            }
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF, or None while RAM is disabled or a clock register is selected
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enable || self.ram_select > 0x03 { return None }
+        Some((self.ram_select as usize * 0x2000) | (addr as usize & 0x1FFF))
+    }
+
+    /// Clock register selected in place of RAM, if enabled
+    fn rtc_register(&self) -> Option<u8> {
+        match self.ram_select {
+            0x08..=0x0C if self.ram_enable && self.rtc.is_some() => Some(self.ram_select),
+            _ => None,
+        }
+    }
+
+    pub fn read_rtc(&self) -> Option<u8> {
+        let register = self.rtc_register()?;
+        self.rtc.as_ref().map(|rtc| rtc.read(register))
+    }
+
+    /// Returns false if the write should go to RAM instead
+    pub fn write_rtc(&mut self, byte: u8) -> bool {
+        let Some(register) = self.rtc_register() else { return false };
+        if let Some(rtc) = &mut self.rtc { rtc.write(register, byte) }
+        true
+    }
+}
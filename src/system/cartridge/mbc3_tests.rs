@@ -0,0 +1,133 @@
+use super::Cartridge;
+use super::cartridge_tests::banked_cartridge;
+use super::rtc::CYCLES_PER_SECOND;
+
+fn run_seconds(cartridge: &mut Cartridge, seconds: u32) {
+    for _ in 0..seconds * (CYCLES_PER_SECOND / 0x80) { cartridge.update_cycle(0x80) }
+}
+
+fn latch(cartridge: &mut Cartridge) {
+    cartridge.write_rom(0x6000, 0x00);
+    cartridge.write_rom(0x6000, 0x01);
+}
+
+/// Latched clock registers 08-0C
+fn read_rtc(cartridge: &mut Cartridge) -> [u8; 5] {
+    let mut registers = [0; 5];
+    for (register, byte) in (0x08..=0x0C).zip(registers.iter_mut()) {
+        cartridge.write_rom(0x4000, register);
+        *byte = cartridge.read_ram(0xA000);
+    }
+    registers
+}
+
+fn write_rtc(cartridge: &mut Cartridge, register: u8, byte: u8) {
+    cartridge.write_rom(0x4000, register);
+    cartridge.write_ram(0xA000, byte);
+}
+
+#[test]
+fn mbc3_banks() {
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x2000, 0x7F);
+    assert_eq!(cartridge.read_rom(0x4001), 127);
+    cartridge.write_rom(0x2000, 0x80); // bank 0 replaced by 1, checking 7 bits
+    assert_eq!(cartridge.read_rom(0x4001), 1);
+
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x03);
+    cartridge.write_ram(0xA000, 0x33);
+    cartridge.write_rom(0x4000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0x00);
+    cartridge.write_rom(0x4000, 0x03);
+    assert_eq!(cartridge.read_ram(0xA000), 0x33);
+}
+
+#[test]
+fn mbc3_rtc_latch() {
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x0000, 0x0A);
+    run_seconds(&mut cartridge, 61);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 0, 0]);
+
+    // registers only change when 0x00 then 0x01 is written
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 0, 0]);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [1, 1, 0, 0, 0]);
+
+    run_seconds(&mut cartridge, 1);
+    assert_eq!(read_rtc(&mut cartridge), [1, 1, 0, 0, 0]);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [2, 1, 0, 0, 0]);
+
+    // clock registers read as open bus while disabled
+    cartridge.write_rom(0x0000, 0x00);
+    cartridge.write_rom(0x4000, 0x08);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc3_rtc_halt() {
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x0000, 0x0A);
+    write_rtc(&mut cartridge, 0x0C, 0x40);
+    run_seconds(&mut cartridge, 5);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 0, 0x40]);
+
+    write_rtc(&mut cartridge, 0x0C, 0x00);
+    run_seconds(&mut cartridge, 5);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [5, 0, 0, 0, 0x00]);
+}
+
+#[test]
+fn mbc3_rtc_day_carry() {
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x0000, 0x0A);
+    // day 511, 23:59:59
+    write_rtc(&mut cartridge, 0x08, 59);
+    write_rtc(&mut cartridge, 0x09, 59);
+    write_rtc(&mut cartridge, 0x0A, 23);
+    write_rtc(&mut cartridge, 0x0B, 0xFF);
+    write_rtc(&mut cartridge, 0x0C, 0x01);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [59, 59, 23, 0xFF, 0x01]);
+
+    run_seconds(&mut cartridge, 1);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 0, 0x80]);
+
+    // carry stays set until written
+    write_rtc(&mut cartridge, 0x08, 59);
+    write_rtc(&mut cartridge, 0x09, 59);
+    write_rtc(&mut cartridge, 0x0A, 23);
+    run_seconds(&mut cartridge, 1);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 1, 0x80]);
+    write_rtc(&mut cartridge, 0x0C, 0x00);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 1, 0x00]);
+}
+
+#[test]
+fn mbc3_rtc_out_of_range() {
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x0000, 0x0A);
+    // counts up to the limit of the register, then wraps without carrying
+    write_rtc(&mut cartridge, 0x08, 62);
+    write_rtc(&mut cartridge, 0x0A, 30);
+    run_seconds(&mut cartridge, 2);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 30, 0, 0]);
+
+    let mut cartridge = banked_cartridge(0x10, 0x200000);
+    cartridge.write_rom(0x0000, 0x0A);
+    write_rtc(&mut cartridge, 0x08, 59);
+    write_rtc(&mut cartridge, 0x09, 59);
+    write_rtc(&mut cartridge, 0x0A, 31);
+    run_seconds(&mut cartridge, 1);
+    latch(&mut cartridge);
+    assert_eq!(read_rtc(&mut cartridge), [0, 0, 0, 0, 0]);
+}
@@ -0,0 +1,51 @@
+/// MBC5 controller, with up to 8 MiB ROM and 128 KiB RAM.
+/// On rumble cartridges, bit 3 of the RAM bank register drives the motor instead.
+pub struct Mbc5 {
+    ram_enable: bool,
+    rom_bank: u16, // 9 bits, where bank 0 can be mapped to 0x4000-0x7FFF
+    ram_bank: u8, // 4 bits, or 3 bits with rumble
+    rumble: Option<bool>, // motor state, on rumble cartridges
+}
+
+impl Mbc5 {
+    pub fn new(rumble: bool) -> Mbc5 {
+        let rumble = if rumble { Some(false) } else { None };
+        Mbc5 { ram_enable: false, rom_bank: 1, ram_bank: 0, rumble }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enable = byte == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | byte as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | (((byte & 0x01) as u16) << 8),
+            0x4000..=0x5FFF => match &mut self.rumble {
+                Some(motor) => {
+                    *motor = byte & 0x08 > 0;
+                    self.ram_bank = byte & 0x07;
+                }
+                None => self.ram_bank = byte & 0x0F,
+            },
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Offset into RAM for 0xA000-0xBFFF, or None while RAM is disabled
+    pub fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enable { return None }
+        Some((self.ram_bank as usize * 0x2000) | (addr as usize & 0x1FFF))
+    }
+
+    /// True while the rumble motor is switched on
+    pub fn rumble(&self) -> bool {
+        self.rumble.unwrap_or(false)
+    }
+}
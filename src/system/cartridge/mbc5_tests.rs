@@ -0,0 +1,58 @@
+use super::Cartridge;
+use super::cartridge_tests::banked_cartridge;
+
+fn mapped_bank(cartridge: &Cartridge) -> u16 {
+    ((cartridge.read_rom(0x4002) as u16) << 8) | cartridge.read_rom(0x4001) as u16
+}
+
+#[test]
+fn mbc5_rom_bank() {
+    let mut cartridge = banked_cartridge(0x1B, 0x800000);
+    assert_eq!(mapped_bank(&cartridge), 1);
+
+    cartridge.write_rom(0x2000, 0xFF);
+    assert_eq!(mapped_bank(&cartridge), 0xFF);
+    cartridge.write_rom(0x3000, 0x01);
+    assert_eq!(mapped_bank(&cartridge), 0x1FF);
+    cartridge.write_rom(0x2FFF, 0x23);
+    assert_eq!(mapped_bank(&cartridge), 0x123);
+
+    // unlike earlier controllers, bank 0 can be mapped to 0x4000-0x7FFF
+    cartridge.write_rom(0x3FFF, 0x00);
+    cartridge.write_rom(0x2000, 0x00);
+    assert_eq!(mapped_bank(&cartridge), 0);
+    assert_eq!(cartridge.read_rom(0x0001), 0);
+}
+
+#[test]
+fn mbc5_ram_bank() {
+    let mut cartridge = banked_cartridge(0x1B, 0x800000);
+    cartridge.write_rom(0x0000, 0x0A);
+    for bank in 0..16 {
+        cartridge.write_rom(0x4000, bank);
+        cartridge.write_ram(0xA000, bank + 0x10);
+    }
+    for bank in 0..16 {
+        cartridge.write_rom(0x4000, bank);
+        assert_eq!(cartridge.read_ram(0xA000), bank + 0x10);
+    }
+    assert!(!cartridge.rumble());
+
+    cartridge.write_rom(0x0000, 0x1A); // only 0x0A enables RAM
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc5_rumble() {
+    let mut cartridge = banked_cartridge(0x1E, 0x800000);
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x01);
+    cartridge.write_ram(0xA000, 0x11);
+
+    // bit 3 switches the motor, without changing the RAM bank
+    cartridge.write_rom(0x4000, 0x09);
+    assert!(cartridge.rumble());
+    assert_eq!(cartridge.read_ram(0xA000), 0x11);
+    cartridge.write_rom(0x4000, 0x01);
+    assert!(!cartridge.rumble());
+}
@@ -0,0 +1,90 @@
+use crate::system::cartridge::eeprom::Eeprom;
+
+/// Accelerometer reading while the cartridge is held level
+pub const ACCELEROMETER_CENTER: u16 = 0x81D0;
+/// Change in the accelerometer reading for 1 g of tilt
+pub const ACCELEROMETER_PER_G: f32 = 0x70 as f32;
+
+/// MBC7 controller, with up to 2 MiB ROM, a two-axis accelerometer
+/// and a 93LC56 EEPROM, all accessed through registers at 0xA000-0xAFFF
+pub struct Mbc7 {
+    ram_enable1: bool, // both enables are needed to map the registers
+    ram_enable2: bool,
+    rom_bank: u8, // 7 bits
+    tilt: (u16, u16), // accelerometer x and y
+    latched: (u16, u16), // accelerometer as of the last latch, read by the CPU
+    latch_erased: bool, // 0x55 was written, so 0xAA latches the accelerometer
+    pub eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new() -> Mbc7 {
+        let center = (ACCELEROMETER_CENTER, ACCELEROMETER_CENTER);
+        Mbc7 {
+            ram_enable1: false,
+            ram_enable2: false,
+            rom_bank: 1,
+            tilt: center,
+            latched: (0x8000, 0x8000),
+            latch_erased: false,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    pub fn write_register(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enable1 = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = byte & 0x7F,
+            0x4000..=0x5FFF => self.ram_enable2 = byte == 0x40,
+            _ => {}
+        }
+    }
+
+    /// Offset into ROM for 0x0000-0x7FFF, to be wrapped to the ROM size
+    pub fn rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        (bank as usize * 0x4000) | (addr as usize & 0x3FFF)
+    }
+
+    /// Sets the accelerometer, in g along each axis, clamped to what the registers can hold
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        let axis = |g: f32| (ACCELEROMETER_CENTER as f32 + g * ACCELEROMETER_PER_G).clamp(0.0, u16::MAX as f32) as u16;
+        self.tilt = (axis(x), axis(y));
+    }
+
+    /// Register at 0xA000-0xBFFF, selected by bits 4-7 of the address.
+    /// Reads 0xFF while the registers are disabled.
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enable1 || !self.ram_enable2 || addr >= 0xB000 { return 0xFF }
+        match (addr >> 4) & 0x0F {
+            0x2 => self.latched.0 as u8,
+            0x3 => (self.latched.0 >> 8) as u8,
+            0x4 => self.latched.1 as u8,
+            0x5 => (self.latched.1 >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    /// Returns true if the EEPROM contents changed
+    pub fn write_ram(&mut self, addr: u16, byte: u8) -> bool {
+        if !self.ram_enable1 || !self.ram_enable2 || addr >= 0xB000 { return false }
+        match ((addr >> 4) & 0x0F, byte) {
+            (0x0, 0x55) => {
+                self.latched = (0x8000, 0x8000);
+                self.latch_erased = true;
+            }
+            (0x1, 0xAA) if self.latch_erased => {
+                self.latched = self.tilt;
+                self.latch_erased = false;
+            }
+            (0x8, _) => return self.eeprom.write(byte),
+            _ => {}
+        }
+        false
+    }
+}
@@ -0,0 +1,132 @@
+use super::Cartridge;
+use super::cartridge_tests::banked_cartridge;
+use super::eeprom::EEPROM_LEN;
+use super::mbc7::ACCELEROMETER_CENTER;
+
+/// MBC7+SENSOR+RUMBLE+RAM+BATTERY cartridge with its registers mapped,
+/// and each 16 KiB ROM bank starting with its bank number
+fn mbc7_cartridge() -> Cartridge {
+    let mut cartridge = banked_cartridge(0x22, 0x100000);
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x40);
+    cartridge
+}
+
+fn read_accelerometer(cartridge: &Cartridge) -> (u16, u16) {
+    let word = |addr: u16| ((cartridge.read_ram(addr + 0x10) as u16) << 8) | cartridge.read_ram(addr) as u16;
+    (word(0xA020), word(0xA040))
+}
+
+/// Clocks bits into the EEPROM MSB first, with chip select held high
+fn send_bits(cartridge: &mut Cartridge, bits: u32, len: u8) {
+    for i in (0..len).rev() {
+        let data_in = (((bits >> i) & 1) as u8) << 1;
+        cartridge.write_ram(0xA080, 0x80 | data_in);
+        cartridge.write_ram(0xA080, 0xC0 | data_in);
+    }
+}
+
+/// Clocks a word out of the EEPROM after a read command
+fn receive_word(cartridge: &mut Cartridge) -> u16 {
+    let mut word = 0;
+    for _ in 0..16 {
+        cartridge.write_ram(0xA080, 0x80);
+        cartridge.write_ram(0xA080, 0xC0);
+        word = (word << 1) | (cartridge.read_ram(0xA080) & 0x01) as u16;
+    }
+    word
+}
+
+/// Start bit, opcode and address, then releases chip select once done
+fn eeprom_command(cartridge: &mut Cartridge, opcode: u32, address: u32, data: Option<u16>) {
+    send_bits(cartridge, (1 << 10) | (opcode << 8) | address, 11);
+    if let Some(data) = data { send_bits(cartridge, data as u32, 16) }
+    cartridge.write_ram(0xA080, 0x00);
+}
+
+fn read_eeprom(cartridge: &mut Cartridge, address: u32) -> u16 {
+    send_bits(cartridge, 0b110_0000_0000 | address, 11);
+    assert_eq!(cartridge.read_ram(0xA080) & 0x01, 0); // dummy bit
+    let word = receive_word(cartridge);
+    cartridge.write_ram(0xA080, 0x00);
+    word
+}
+
+#[test]
+fn mbc7_registers() {
+    let mut cartridge = mbc7_cartridge();
+    cartridge.write_rom(0x2000, 0x3F);
+    assert_eq!(cartridge.read_rom(0x4001), 63);
+
+    assert_eq!(cartridge.read_ram(0xA060), 0x00);
+    assert_eq!(cartridge.read_ram(0xB020), 0xFF);
+    cartridge.write_rom(0x4000, 0x00); // both enables are needed
+    assert_eq!(cartridge.read_ram(0xA060), 0xFF);
+}
+
+#[test]
+fn mbc7_accelerometer() {
+    let mut cartridge = mbc7_cartridge();
+    cartridge.set_tilt(1.0, -0.5);
+
+    // erasing the latch resets the registers, and latching needs an erase first
+    cartridge.write_ram(0xA000, 0x55);
+    assert_eq!(read_accelerometer(&cartridge), (0x8000, 0x8000));
+    cartridge.write_ram(0xA010, 0xAA);
+    assert_eq!(read_accelerometer(&cartridge), (ACCELEROMETER_CENTER + 0x70, ACCELEROMETER_CENTER - 0x38));
+
+    cartridge.set_tilt(0.0, 0.0);
+    cartridge.write_ram(0xA010, 0xAA);
+    assert_eq!(read_accelerometer(&cartridge), (ACCELEROMETER_CENTER + 0x70, ACCELEROMETER_CENTER - 0x38));
+    cartridge.write_ram(0xA000, 0x55);
+    cartridge.write_ram(0xA010, 0xAA);
+    assert_eq!(read_accelerometer(&cartridge), (ACCELEROMETER_CENTER, ACCELEROMETER_CENTER));
+}
+
+#[test]
+fn mbc7_eeprom() {
+    let mut cartridge = mbc7_cartridge();
+    assert_eq!(read_eeprom(&mut cartridge, 0x05), 0xFFFF);
+
+    // writes are ignored until enabled
+    eeprom_command(&mut cartridge, 0b01, 0x05, Some(0x1234));
+    assert!(!cartridge.ram_written());
+    assert_eq!(read_eeprom(&mut cartridge, 0x05), 0xFFFF);
+
+    eeprom_command(&mut cartridge, 0b00, 0xC0, None); // EWEN
+    eeprom_command(&mut cartridge, 0b01, 0x05, Some(0x1234));
+    eeprom_command(&mut cartridge, 0b01, 0x06, Some(0xABCD));
+    assert!(cartridge.ram_written());
+    assert_eq!(cartridge.read_ram(0xA080) & 0x01, 0x01); // ready
+    assert_eq!(read_eeprom(&mut cartridge, 0x05), 0x1234);
+
+    // reads continue into the next word
+    send_bits(&mut cartridge, 0b110_0000_0101, 11);
+    assert_eq!(receive_word(&mut cartridge), 0x1234);
+    assert_eq!(receive_word(&mut cartridge), 0xABCD);
+    cartridge.write_ram(0xA080, 0x00);
+
+    eeprom_command(&mut cartridge, 0b11, 0x05, None); // ERASE
+    assert_eq!(read_eeprom(&mut cartridge, 0x05), 0xFFFF);
+    eeprom_command(&mut cartridge, 0b00, 0x40, Some(0x5A5A)); // WRAL
+    assert_eq!(read_eeprom(&mut cartridge, 0x7F), 0x5A5A);
+    eeprom_command(&mut cartridge, 0b00, 0x00, None); // EWDS
+    eeprom_command(&mut cartridge, 0b00, 0x80, None); // ERAL, now ignored
+    assert_eq!(read_eeprom(&mut cartridge, 0x00), 0x5A5A);
+}
+
+#[test]
+fn mbc7_save() {
+    let mut cartridge = mbc7_cartridge();
+    assert!(cartridge.has_battery());
+    eeprom_command(&mut cartridge, 0b00, 0xC0, None);
+    eeprom_command(&mut cartridge, 0b01, 0x01, Some(0x1234));
+    let data = cartridge.save_data();
+    assert_eq!(data.len(), EEPROM_LEN);
+    assert_eq!(&data[0x00..0x04], &[0xFF, 0xFF, 0x34, 0x12]);
+
+    let mut loaded = mbc7_cartridge();
+    loaded.load_save_data(&data).unwrap();
+    assert_eq!(read_eeprom(&mut loaded, 0x01), 0x1234);
+    assert!(loaded.load_save_data(&[]).is_err());
+}
@@ -0,0 +1,179 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// M-cycles in one second of emulated time
+pub const CYCLES_PER_SECOND: u32 = 0x100000;
+
+/// Length of the clock state appended to save files, as used by VBA-M, BGB and others:
+/// the 5 counters then the 5 latched registers as little-endian u32s, then a u64 UNIX timestamp
+pub const RTC_TRAILER_LEN: usize = 48;
+
+/// How the real-time clock is advanced
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RtcClock {
+    Emulated, // by the cycles the system runs, deterministic
+    WallClock, // by the host's clock, including time between sessions
+}
+
+/// MBC3 real-time clock, counting seconds, minutes, hours and a 9-bit day counter.
+/// Registers can be set to values the counters would never reach, and then
+/// count up to the limit of their bit width before wrapping to 0 without carrying.
+pub struct Rtc {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halt: bool,
+    day_carry: bool, // day counter overflowed, until cleared
+    latched: [u8; 5], // registers 08-0C as of the last latch
+    clock: RtcClock,
+    cycles: u32, // cycles into the current second, when emulated
+    synced: SystemTime, // time the counters were last brought up to date, on the wall clock
+}
+
+impl Rtc {
+    pub fn new(clock: RtcClock) -> Rtc {
+        Rtc {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            day_carry: false,
+            latched: [0; 5],
+            clock,
+            cycles: 0,
+            synced: SystemTime::now(),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: RtcClock) {
+        self.sync();
+        self.clock = clock;
+        self.synced = SystemTime::now();
+    }
+
+    pub fn update_cycle(&mut self, cycles: u8) {
+        if self.clock != RtcClock::Emulated || self.halt { return }
+        self.cycles += cycles as u32;
+        if self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.advance(1);
+        }
+    }
+
+    /// Copies the counters to the registers read by the CPU
+    pub fn latch(&mut self) {
+        self.sync();
+        self.latched = [self.seconds, self.minutes, self.hours, self.days as u8,
+            ((self.days >> 8) as u8) | ((self.halt as u8) << 6) | ((self.day_carry as u8) << 7)];
+    }
+
+    /// Latched value of register 08-0C
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[(register - 0x08) as usize]
+    }
+
+    /// Writes go straight to the counters, and are only visible after the next latch
+    pub fn write(&mut self, register: u8, byte: u8) {
+        self.sync();
+        match register {
+            0x08 => {
+                self.seconds = byte & 0x3F;
+                self.cycles = 0; // writing seconds resets the divider
+            }
+            0x09 => self.minutes = byte & 0x3F,
+            0x0A => self.hours = byte & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | byte as u16,
+            0x0C => {
+                self.days = (self.days & 0xFF) | (((byte & 0x01) as u16) << 8);
+                self.halt = byte & 0x40 > 0;
+                self.day_carry = byte & 0x80 > 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Clock state to append to a save file
+    pub fn to_trailer(&mut self) -> [u8; RTC_TRAILER_LEN] {
+        self.sync();
+        let registers = [self.seconds, self.minutes, self.hours, self.days as u8,
+            ((self.days >> 8) as u8) | ((self.halt as u8) << 6) | ((self.day_carry as u8) << 7)];
+        let mut trailer = [0; RTC_TRAILER_LEN];
+        for (i, byte) in registers.iter().chain(self.latched.iter()).enumerate() {
+            trailer[i * 4] = *byte;
+        }
+        let timestamp = match self.clock {
+            RtcClock::WallClock => self.synced,
+            RtcClock::Emulated => SystemTime::now(),
+        };
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).map_or(0, |timestamp| timestamp.as_secs());
+        trailer[40..48].copy_from_slice(&timestamp.to_le_bytes());
+        trailer
+    }
+
+    /// Restores clock state from a save file. On the wall clock, the
+    /// time since the save was written is added to the counters.
+    pub fn load_trailer(&mut self, trailer: &[u8; RTC_TRAILER_LEN]) {
+        let register = |i: usize| trailer[i * 4];
+        self.write(0x08, register(0));
+        self.write(0x09, register(1));
+        self.write(0x0A, register(2));
+        self.write(0x0B, register(3));
+        self.write(0x0C, register(4));
+        for i in 0..5 { self.latched[i] = register(i + 5) }
+
+        let timestamp = u64::from_le_bytes(trailer[40..48].try_into().unwrap());
+        if self.clock == RtcClock::WallClock {
+            self.synced = UNIX_EPOCH + Duration::from_secs(timestamp);
+            self.sync();
+        }
+    }
+
+    /// Brings the counters up to date with the wall clock
+    fn sync(&mut self) {
+        if self.clock != RtcClock::WallClock { return }
+        let now = SystemTime::now();
+        if self.halt {
+            self.synced = now;
+            return;
+        }
+        // time going backwards on the host is ignored
+        let elapsed = now.duration_since(self.synced).map_or(0, |elapsed| elapsed.as_secs());
+        self.advance(elapsed);
+        // fractions of a second are kept for the next sync
+        self.synced += Duration::from_secs(elapsed);
+    }
+
+    /// Advances the counters by the given number of seconds
+    pub fn advance(&mut self, seconds: u64) {
+        let mut seconds = seconds;
+        // step through out of range values one second at a time, until they wrap
+        while seconds > 0 && (self.seconds >= 60 || self.minutes >= 60 || self.hours >= 24) {
+            self.tick();
+            seconds -= 1;
+        }
+        if seconds == 0 { return }
+        let total = seconds + self.seconds as u64 + 60 * self.minutes as u64
+            + 3600 * self.hours as u64 + 86400 * self.days as u64;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        let days = total / 86400;
+        if days >= 0x200 { self.day_carry = true }
+        self.days = (days % 0x200) as u16;
+    }
+
+    fn tick(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 { return }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 { return }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 { return }
+        self.hours = 0;
+        self.days = (self.days + 1) & 0x1FF;
+        if self.days == 0 { self.day_carry = true }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::system::cartridge::{Cartridge, CartridgeError};
+
+/// Save file holding the RAM of a battery-backed cartridge
+pub struct SaveFile {
+    path: PathBuf,
+}
+
+impl SaveFile {
+    pub fn new(path: PathBuf) -> SaveFile {
+        SaveFile { path }
+    }
+
+    /// Save file next to the ROM, with its extension replaced by .sav
+    pub fn for_rom(rom_path: &Path) -> SaveFile {
+        SaveFile::new(rom_path.with_extension("sav"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the save into the cartridge, returning false if there's no save yet
+    pub fn load(&self, cartridge: &mut Cartridge) -> Result<bool, CartridgeError> {
+        match fs::read(&self.path) {
+            Ok(data) => cartridge.load_save_data(&data).map(|_| true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Writes the save to a temporary file that replaces the old save once complete,
+    /// so a crash or power loss part way through leaves the old save intact
+    pub fn write(&self, cartridge: &mut Cartridge) -> io::Result<()> {
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let mut temp_file = File::create(&temp_path)?;
+        temp_file.write_all(&cartridge.save_data())?;
+        // contents must reach the disk before the rename can expose them
+        temp_file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+        // and on Unix, the rename is only durable once the directory is synced
+        #[cfg(unix)]
+        {
+            let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the save if RAM has changed since it was last written
+    pub fn write_if_changed(&self, cartridge: &mut Cartridge) -> io::Result<()> {
+        if cartridge.ram_written() { self.write(cartridge)? }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Cartridge, CartridgeError};
+use super::cartridge_tests::rom_image;
+use super::huc3::HUC3_TRAILER_LEN;
+use super::rtc::{RtcClock, RTC_TRAILER_LEN};
+use super::save::SaveFile;
+
+/// MBC3+TIMER+RAM+BATTERY cartridge with 8 KiB RAM
+fn mbc3_cartridge(clock: RtcClock) -> Cartridge {
+    let rom = rom_image(0x8000, &[(0x0147, 0x10), (0x0149, 0x02)]);
+    Cartridge::from_bytes_with_clock(rom, clock).unwrap()
+}
+
+#[test]
+fn save_ram() {
+    let path = env::temp_dir().join(format!("orion-save-ram-{}.sav", process::id()));
+    let save_file = SaveFile::new(path.clone());
+    // MBC1+RAM+BATTERY
+    let mut cartridge = Cartridge::from_bytes(rom_image(0x8000, &[(0x0147, 0x03), (0x0149, 0x02)])).unwrap();
+    assert!(cartridge.has_battery());
+    assert!(!save_file.load(&mut cartridge).unwrap());
+
+    cartridge.write_rom(0x0000, 0x0A);
+    save_file.write_if_changed(&mut cartridge).unwrap();
+    assert!(!path.exists());
+    cartridge.write_ram(0xA000, 0x12);
+    cartridge.write_ram(0xBFFF, 0x34);
+    assert!(cartridge.ram_written());
+    save_file.write_if_changed(&mut cartridge).unwrap();
+    assert!(!cartridge.ram_written());
+    assert_eq!(fs::read(&path).unwrap().len(), 0x2000);
+
+    let mut cartridge = Cartridge::from_bytes(rom_image(0x8000, &[(0x0147, 0x03), (0x0149, 0x02)])).unwrap();
+    assert!(save_file.load(&mut cartridge).unwrap());
+    cartridge.write_rom(0x0000, 0x0A);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+    assert_eq!(cartridge.read_ram(0xBFFF), 0x34);
+
+    // a save for a different cartridge is rejected
+    let mut cartridge = Cartridge::from_bytes(rom_image(0x8000, &[(0x0147, 0x03), (0x0149, 0x03)])).unwrap();
+    assert!(matches!(save_file.load(&mut cartridge),
+        Err(CartridgeError::SaveSizeMismatch { expected: 0x8000, actual: 0x2000 })));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn save_rtc_trailer() {
+    let mut cartridge = mbc3_cartridge(RtcClock::Emulated);
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x56);
+    cartridge.write_rom(0x4000, 0x09); // minutes
+    cartridge.write_ram(0xA000, 42);
+    cartridge.write_rom(0x4000, 0x0C); // day high, halt
+    cartridge.write_ram(0xA000, 0x41);
+
+    let data = cartridge.save_data();
+    assert_eq!(data.len(), 0x2000 + RTC_TRAILER_LEN);
+    assert_eq!(data[0], 0x56);
+    assert_eq!(data[0x2000 + 4..0x2000 + 8], [42, 0, 0, 0]);
+    assert_eq!(data[0x2000 + 16..0x2000 + 20], [0x41, 0, 0, 0]);
+
+    let mut cartridge = mbc3_cartridge(RtcClock::Emulated);
+    cartridge.load_save_data(&data).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x6000, 0x00);
+    cartridge.write_rom(0x6000, 0x01);
+    cartridge.write_rom(0x4000, 0x09);
+    assert_eq!(cartridge.read_ram(0xA000), 42);
+    cartridge.write_rom(0x4000, 0x0C);
+    assert_eq!(cartridge.read_ram(0xA000), 0x41);
+
+    // saves without a trailer are accepted
+    let mut cartridge = mbc3_cartridge(RtcClock::Emulated);
+    cartridge.load_save_data(&data[..0x2000]).unwrap();
+}
+
+#[test]
+fn save_rtc_wall_clock() {
+    // clock advances by the time since the save was written
+    let mut data = vec![0; 0x2000 + RTC_TRAILER_LEN];
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - 3 * 86400 - 60;
+    data[0x2000 + 40..].copy_from_slice(&timestamp.to_le_bytes());
+
+    let mut cartridge = mbc3_cartridge(RtcClock::WallClock);
+    cartridge.load_save_data(&data).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x6000, 0x00);
+    cartridge.write_rom(0x6000, 0x01);
+    cartridge.write_rom(0x4000, 0x09);
+    assert!(cartridge.read_ram(0xA000) >= 1); // minutes, allowing for a slow test
+    cartridge.write_rom(0x4000, 0x0B);
+    assert_eq!(cartridge.read_ram(0xA000), 3);
+}
+
+#[test]
+fn save_huc3_trailer() {
+    let huc3_cartridge = || Cartridge::from_bytes_with_clock(rom_image(0x8000, &[(0x0147, 0xFE), (0x0149, 0x03)]), RtcClock::Emulated).unwrap();
+    // sends a command through mode 0x0B and runs it through the semaphore
+    let command = |cartridge: &mut Cartridge, command: u8| {
+        cartridge.write_rom(0x0000, 0x0B);
+        cartridge.write_ram(0xA000, command);
+        cartridge.write_rom(0x0000, 0x0D);
+        cartridge.write_ram(0xA000, 0xFE);
+        cartridge.write_rom(0x0000, 0x0C);
+        cartridge.read_ram(0xA000)
+    };
+    let mut cartridge = huc3_cartridge();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x56);
+    // 1439 minutes (0x59F) and 0xABC days into memory 00-05 and the clock, then 0x7 at 0x10
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x50);
+    for nibble in [0xF, 0x9, 0x5, 0xC, 0xB, 0xA] { command(&mut cartridge, 0x30 | nibble); }
+    command(&mut cartridge, 0x61);
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x51);
+    command(&mut cartridge, 0x37);
+
+    let data = cartridge.save_data();
+    assert_eq!(data.len(), 0x8000 + HUC3_TRAILER_LEN);
+    assert_eq!(data[0], 0x56);
+    assert_eq!(data[0x8000..0x8000 + 8], [0x9F, 0x05, 0, 0, 0xBC, 0x0A, 0, 0]);
+
+    let mut cartridge = huc3_cartridge();
+    cartridge.load_save_data(&data).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    assert_eq!(cartridge.read_ram(0xA000), 0x56);
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x51);
+    assert_eq!(command(&mut cartridge, 0x10), 0x17);
+    // memory 00-05 is overwritten with the restored clock's time
+    command(&mut cartridge, 0x60);
+    command(&mut cartridge, 0x40);
+    command(&mut cartridge, 0x50);
+    let nibbles: Vec<u8> = (0..6).map(|_| command(&mut cartridge, 0x10) & 0x0F).collect();
+    assert_eq!(nibbles, [0xF, 0x9, 0x5, 0xC, 0xB, 0xA]);
+
+    // saves without a trailer are accepted
+    let mut cartridge = huc3_cartridge();
+    cartridge.load_save_data(&data[..0x8000]).unwrap();
+}
@@ -1,59 +1,272 @@
 pub mod regfile;
 pub mod instruction;
+pub mod disasm;
+#[cfg(test)]
+#[allow(non_snake_case)]
 pub mod cpu_tests;
+#[cfg(test)]
+pub mod disasm_tests;
 
 use std::error::Error;
+use std::fmt;
+use std::io::Write;
 use std::result::Result;
 
 use crate::system::cpu::regfile::Regfile;
 use crate::system::cpu::instruction::*;
-use crate::system::memory::{Memory, self};
+use crate::system::memory::Memory;
+use crate::system::model::Model;
 pub struct CPU {
     regfile: Regfile,
     pc: u16,
     sp: u16,
     ime: bool, // Interrupt Master Enable Flag
-    scheduled_ime: bool // IME takes one instruction to switch to true
+    scheduled_ime: bool, // IME takes one instruction to switch to true
+    state: CpuState,
+    halt_bug: bool, // byte after HALT is read twice when HALT is skipped
+    cycles: u8, // cycles consumed by the last call to run
+    illegal_opcode_mode: IllegalOpcodeMode,
+    detect_lockups: bool, // HALT that can never wake is returned as CpuError::Lockup
+    trace: Option<Box<dyn Write>>, // instruction log, see trace_line
 }
 
+/// How the CPU handles the undefined opcodes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IllegalOpcodeMode {
+    Error, // run returns a CpuError
+    Lock, // CPU hangs as on hardware, while the rest of the system keeps running
+}
+
+/// Power state of the CPU, changed by the HALT and STOP instructions
+#[derive(PartialEq, Debug)]
+pub enum CpuState {
+    Running,
+    Halted, // no fetching until IE & IF is non-zero
+    Stopped, // no fetching until joypad input
+    Locked(u8), // no fetching ever again after the given illegal opcode
+}
+
+/// Reasons the CPU can't continue, with the address and opcode of the
+/// instruction responsible. For CB-prefixed instructions, `opcode` is the
+/// byte following the prefix.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CpuError {
+    IllegalOpcode { pc: u16, opcode: u8, prefixed: bool },
+    UnimplementedOpcode { pc: u16, opcode: u8, prefixed: bool },
+    Lockup { pc: u16, opcode: u8, prefixed: bool }, // HALT with IME and IE clear, see set_lockup_detection
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (description, pc, opcode, prefixed) = match *self {
+            CpuError::IllegalOpcode { pc, opcode, prefixed } => ("illegal opcode", pc, opcode, prefixed),
+            CpuError::UnimplementedOpcode { pc, opcode, prefixed } => ("unimplemented opcode", pc, opcode, prefixed),
+            CpuError::Lockup { pc, opcode, prefixed } => ("CPU locked up by opcode", pc, opcode, prefixed),
+        };
+        let prefix = if prefixed { "$CB " } else { "" };
+        write!(f, "{} {}${:02X} at ${:04X}", description, prefix, opcode, pc)
+    }
+}
+
+impl Error for CpuError {}
+
 impl CPU {
     pub fn new() -> CPU {
+        CPU::with_illegal_opcode_mode(IllegalOpcodeMode::Error)
+    }
+
+    pub fn with_illegal_opcode_mode(illegal_opcode_mode: IllegalOpcodeMode) -> CPU {
         let regfile: Regfile = Regfile::new();
         let pc: u16 = 0;
         let sp: u16 = 0xFFFE;
         let ime: bool = true;
         let scheduled_ime = false;
-        CPU {regfile, pc, sp, ime, scheduled_ime}
+        let state = CpuState::Running;
+        let halt_bug = false;
+        let cycles = 0;
+        let detect_lockups = false;
+        let trace = None;
+        CPU {regfile, pc, sp, ime, scheduled_ime, state, halt_bug, cycles, illegal_opcode_mode, detect_lockups, trace}
     }
 
-    pub fn run(&mut self, memory: &mut Memory) -> Result<u8, &'static str> {
+    /// Registers as found at power-on, before the boot ROM has run
+    pub fn set_power_on_state(&mut self) {
+        self.regfile = Regfile::new();
+        self.pc = 0x0000;
+        self.ime = false;
+        self.scheduled_ime = false;
+        self.state = CpuState::Running;
+        self.halt_bug = false;
+    }
+
+    /// Registers as left by the boot ROM of the given model, with
+    /// the cartridge's header already loaded into memory
+    pub fn set_post_boot_state(&mut self, model: Model, memory: &Memory) {
+        let [a, b, c, d, e, h, l] = model.post_boot_registers();
+        self.regfile = Regfile { r_a: a, r_b: b, r_c: c, r_d: d, r_e: e, r_h: h, r_l: l,
+            r_f: model.post_boot_flags(memory.read_byte(0x014D)) };
+        self.pc = 0x0100;
+        self.sp = 0xFFFE;
+        self.ime = false;
+        self.scheduled_ime = false;
+        self.state = CpuState::Running;
+        self.halt_bug = false;
+    }
+
+    pub fn run(&mut self, memory: &mut Memory) -> Result<u8, CpuError> {
+        self.cycles = 0;
+        match self.state {
+            CpuState::Halted => return Ok(self.run_halted(memory)),
+            CpuState::Stopped => return Ok(self.run_stopped(memory)),
+            CpuState::Locked(opcode) => return Ok(self.run_locked(memory, opcode)),
+            CpuState::Running => {}
+        }
+        if self.trace.is_some() { self.write_trace(memory) }
         let (opcode_byte, next_byte) = self.fetch(memory);
         // decode
         let instruction = Instruction::from_byte(opcode_byte, next_byte);
-        // pass instruction cycle count to memory, to update attached components by corresponding timesteps
-        memory.update_cycle(instruction.cycle_len);
+        let (cycle_len, cycle_len_not_taken) = (instruction.cycle_len, instruction.cycle_len_not_taken());
+
+        if self.halt_bug {
+            // PC failed to increment after the opcode fetch, so operands
+            // are read starting from the opcode byte itself
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
 
         // ime set if scheduled by previous instruction and not reset by latest instruction
         let ime_flag = self.scheduled_ime;
-        let result = self.execute(instruction, memory)?;
+        let result = self.execute(instruction, opcode_byte, next_byte, memory)?;
+        // timing comes from the bus accesses made, so it should agree with the opcode table
+        debug_assert!(self.cycles == cycle_len || self.cycles == cycle_len_not_taken,
+            "opcode {:02X?} {:02X?} took {} cycles", opcode_byte, next_byte, self.cycles);
         if ime_flag && self.scheduled_ime { self.ime = true }
-        // interrupts checked after every instruction
-        if self.ime { 
+        // interrupts checked after every instruction, unless an illegal opcode locked the CPU
+        if self.ime && !matches!(self.state, CpuState::Locked(_)) { 
             self.scheduled_ime = false;
             self.check_interrupts(memory); 
         }
         Ok(result)
     }
 
-    fn fetch(&mut self, memory: &Memory) -> (u8,u8) {
-        (memory.read_byte(self.pc), memory.read_byte(self.pc+1))
+    fn run_halted(&mut self, memory: &mut Memory) -> u8 {
+        // no instructions are fetched, but the rest of the system keeps running
+        self.internal_cycle(memory);
+        if memory.interrupts.pending() {
+            // wakes regardless of IME, but the interrupt is only serviced if IME is set
+            self.state = CpuState::Running;
+            if self.ime { self.check_interrupts(memory) }
+        }
+        0x76
+    }
+
+    fn run_stopped(&mut self, memory: &mut Memory) -> u8 {
+        // system clock is halted, so peripherals are not updated
+        if memory.interrupts.joypad_requested() {
+            self.state = CpuState::Running;
+        }
+        0x10
+    }
+
+    fn run_locked(&mut self, memory: &mut Memory, opcode: u8) -> u8 {
+        // interrupts are ignored, but peripherals are still clocked
+        self.internal_cycle(memory);
+        opcode
+    }
+
+    fn halt(&mut self, memory: &Memory) {
+        if !self.ime && memory.interrupts.pending() {
+            // HALT is skipped and the following byte is read twice
+            self.halt_bug = true;
+        }
+        else {
+            self.state = CpuState::Halted;
+        }
+    }
+
+    fn stop(&mut self, memory: &mut Memory) {
+        memory.write_byte(0xFF04, 0); // resets DIV
+        self.state = CpuState::Stopped;
+    }
+
+    pub fn get_state(&self) -> &CpuState { &self.state }
+
+    /// Diagnostic for HALT run with IME clear and nothing enabled in IE, which nothing can wake.
+    /// When enabled, run returns CpuError::Lockup instead of halting forever.
+    pub fn set_lockup_detection(&mut self, enabled: bool) {
+        self.detect_lockups = enabled;
     }
 
-    fn execute(&mut self, instruction: Instruction, memory: &mut Memory) -> Result<u8, &'static str> {
+    /// Log the CPU state before each instruction is fetched, one line per instruction
+    /// in the format compared by gameboy-doctor. Disabled by passing None.
+    pub fn set_trace(&mut self, trace: Option<Box<dyn Write>>) {
+        self.trace = trace;
+    }
+
+    /// Registers and the 4 bytes at PC, e.g.
+    /// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,50,01`
+    pub fn trace_line(&self, memory: &Memory) -> String {
+        let (regfile, sp, pc) = (&self.regfile, self.sp, self.pc);
+        let pcmem = |offset: u16| memory.read_byte(pc.wrapping_add(offset));
+        format!("A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            regfile.r_a, regfile.r_f, regfile.r_b, regfile.r_c, regfile.r_d, regfile.r_e, regfile.r_h, regfile.r_l,
+            sp, pc, pcmem(0), pcmem(1), pcmem(2), pcmem(3))
+    }
+
+    fn write_trace(&mut self, memory: &Memory) {
+        let line = self.trace_line(memory);
+        if let Some(trace) = &mut self.trace {
+            // logging is given up on rather than stopping emulation
+            if writeln!(trace, "{}", line).is_err() { self.trace = None }
+        }
+    }
+
+    /// Cycles consumed by the last call to run, including interrupt dispatch
+    pub fn get_cycles(&self) -> u8 { self.cycles }
+
+    fn fetch(&mut self, memory: &mut Memory) -> (u8,u8) {
+        let next_addr = if self.halt_bug { self.pc } else { self.pc.wrapping_add(1) };
+        let opcode_byte = self.read_cycle(memory, self.pc);
+        // next byte is only fetched here for the CB prefix, other operands are read by execute
+        let next_byte = if opcode_byte == 0xCB { self.read_cycle(memory, next_addr) } else { 0 };
+        (opcode_byte, next_byte)
+    }
+
+    // Bus accesses, each taking one M-cycle during which the rest of the
+    // system is updated before the access is made
+
+    fn read_cycle(&mut self, memory: &mut Memory, addr: u16) -> u8 {
+        self.internal_cycle(memory);
+        memory.read_byte(addr)
+    }
+
+    fn write_cycle(&mut self, memory: &mut Memory, addr: u16, byte: u8) {
+        self.internal_cycle(memory);
+        memory.write_byte(addr, byte);
+    }
+
+    fn internal_cycle(&mut self, memory: &mut Memory) {
+        // M-cycle without a bus access
+        self.cycles += 1;
+        memory.update_cycle(1);
+    }
+
+    fn execute(&mut self, instruction: Instruction, instr_byte: u8, next_byte: u8, memory: &mut Memory) -> Result<u8, CpuError> {
         use crate::system::cpu::instruction::InstructionType::*;
-        let a16 = memory.read_next_word(self.pc);
-        let d8 = memory.read_byte(self.pc+1);
-        let instr_byte = memory.read_byte(self.pc);
+        let (pc, prefixed) = (self.pc, instr_byte == 0xCB);
+        let opcode = if prefixed { next_byte } else { instr_byte };
+        let unimplemented = CpuError::UnimplementedOpcode { pc, opcode, prefixed };
+        // immediate operands follow the opcode, with 16-bit values stored little-endian
+        let operand_len = match instruction.op {
+            _ if instr_byte == 0xCB => 0, // CB opcode byte was already fetched
+            Opcode::STOP => 0, // byte after STOP is skipped, not read
+            _ => instruction.instr_len - 1
+        };
+        let d8 = if operand_len > 0 { self.read_cycle(memory, self.pc.wrapping_add(1)) } else { 0 };
+        let a16 = if operand_len > 1 {
+            let msb = self.read_cycle(memory, self.pc.wrapping_add(2));
+            ((msb as u16) << 8) | d8 as u16
+        } else { 0 };
         self.pc_add(instruction.instr_len);
         match instruction.instr_type {
             Arithmetic(target) => {
@@ -68,8 +281,14 @@ impl CPU {
                         ArithmeticArg::H => self.regfile.r_h,
                         ArithmeticArg::L => self.regfile.r_l,
                         ArithmeticArg::HL => {
-                            let addr = self.regfile.get_hl();
-                            memory.read_byte(addr)   
+                            match instruction.op {
+                                // read by increment/decrement instead
+                                Opcode::INC | Opcode::DEC => 0,
+                                _ => {
+                                    let addr = self.regfile.get_hl();
+                                    self.read_cycle(memory, addr)
+                                }
+                            }
                         }
                         ArithmeticArg::D8 => d8,
                         ArithmeticArg::None => 0,
@@ -112,7 +331,7 @@ impl CPU {
                         self.regfile.set_sub(false);
                         self.regfile.set_half_carry(false);
                     },
-                    _ => {}
+                    _ => return Err(unimplemented)
                 }
             }
             Rotate(target) => {
@@ -143,10 +362,16 @@ impl CPU {
                     Opcode::RR => {
                         self.rotate_right(memory, target, instruction.op)
                     }
+                    Opcode::SLA => {
+                        self.rotate_left(memory, target, instruction.op)
+                    }
+                    Opcode::SRA | Opcode::SRL => {
+                        self.rotate_right(memory, target, instruction.op)
+                    }
                     Opcode::SWAP => {
                         self.swap_bits(memory, target);
                     }
-                    _ => {}
+                    _ => return Err(unimplemented)
                 }
             }
             Bit(target, bit) => {
@@ -154,7 +379,7 @@ impl CPU {
                     Opcode::BIT => self.bit(memory, target, bit),
                     Opcode::RES => self.set_bit(memory, target, bit, false),
                     Opcode::SET => self.set_bit(memory, target, bit, true),
-                    _ => {}
+                    _ => return Err(unimplemented)
                 }
             }
             Load(target, source) => {
@@ -169,12 +394,12 @@ impl CPU {
                         LoadSource::L => self.regfile.r_l,
                         LoadSource::HL => {
                             let addr = self.regfile.get_hl();
-                            memory.read_byte(addr)
+                            self.read_cycle(memory, addr)
                         }
                         LoadSource::HLI => {
                             // increment HL after fetching
                             let addr = self.regfile.get_hl();
-                            let val = memory.read_byte(addr);
+                            let val = self.read_cycle(memory, addr);
                             let new_hl = addr.wrapping_add(1);
                             self.regfile.set_hl(new_hl);
                             val
@@ -182,30 +407,30 @@ impl CPU {
                         LoadSource::HLD => {
                             // decrement HL after fetching
                             let addr = self.regfile.get_hl();
-                            let val = memory.read_byte(addr);
+                            let val = self.read_cycle(memory, addr);
                             let new_hl = addr.wrapping_sub(1);
                             self.regfile.set_hl(new_hl);
                             val
                         }
                         LoadSource::BC => {
                             let addr = self.regfile.get_bc();
-                            memory.read_byte(addr)
+                            self.read_cycle(memory, addr)
                         }
                         LoadSource::DE => {
                             let addr = self.regfile.get_de();
-                            memory.read_byte(addr)
+                            self.read_cycle(memory, addr)
                         }
                         LoadSource::D8 => d8,
                         LoadSource::A8 => {
                             let addr = (d8 as u16) + 0xFF00;
-                            memory.read_byte(addr)
+                            self.read_cycle(memory, addr)
                         }
                         LoadSource::A16 => {
-                            memory.read_byte(16)
+                            self.read_cycle(memory, a16)
                         }
                         LoadSource::CA => {
                             let addr = (self.regfile.r_c as u16) + 0xFF00;
-                            memory.read_byte(addr)
+                            self.read_cycle(memory, addr)
                         }
                     }
                 };
@@ -219,38 +444,38 @@ impl CPU {
                     LoadTarget::L => self.regfile.r_l = source_val,
                     LoadTarget::HL => {
                         let addr = self.regfile.get_hl();
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                     }
                     LoadTarget::HLI => {
                         let addr = self.regfile.get_hl();
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                         let new_hl = addr.wrapping_add(1);
                         self.regfile.set_hl(new_hl);
                     }
                     LoadTarget::HLD => {
                         let addr = self.regfile.get_hl();
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                         let new_hl = addr.wrapping_sub(1);
                         self.regfile.set_hl(new_hl);
                     }
                     LoadTarget::BC => {
                         let addr = self.regfile.get_bc();
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                     }
                     LoadTarget::DE => {
                         let addr = self.regfile.get_de();
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                     }
                     LoadTarget::A8 => {
                         let addr = (d8 as u16) + 0xFF00;
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                     }
                     LoadTarget::CA => {
                         let addr = (self.regfile.r_c as u16) + 0xFF00;
-                        memory.write_byte(addr, source_val);
+                        self.write_cycle(memory, addr, source_val);
                     }
                     LoadTarget::A16 => {
-                        memory.write_byte(a16, source_val);
+                        self.write_cycle(memory, a16, source_val);
                     }
                 }
             }
@@ -265,10 +490,11 @@ impl CPU {
             }
             LoadMemory16 => {
                 let (msb, lsb) = self.split_u16(self.sp);
-                memory.write_byte(a16, lsb);
-                memory.write_byte(a16.wrapping_add(1), msb);
+                self.write_cycle(memory, a16, lsb);
+                self.write_cycle(memory, a16.wrapping_add(1), msb);
             }
             LoadHL => {
+                self.internal_cycle(memory);
                 let (val, carry) = self.sp.overflowing_add(d8 as u16);
                 self.regfile.set_hl(val);
                 self.regfile.set_carry(carry);
@@ -278,6 +504,7 @@ impl CPU {
                 self.regfile.set_sub(false);
             }
             Add16(source) => {
+                self.internal_cycle(memory);
                 let val = match source {
                     Word16::BC => self.regfile.get_bc(),
                     Word16::DE => self.regfile.get_de(),
@@ -294,6 +521,8 @@ impl CPU {
                 self.regfile.set_sub(false);
             }
             AddSP => {
+                self.internal_cycle(memory);
+                self.internal_cycle(memory);
                 let (new_val, carry) = self.sp.overflowing_add(d8 as u16);
                 self.sp = new_val;
                 self.regfile.set_carry(carry);
@@ -303,6 +532,7 @@ impl CPU {
                 self.regfile.set_sub(false);
             },
             LoadSP => {
+                self.internal_cycle(memory);
                 self.sp = self.regfile.get_hl()
             }
             Push(target) => {
@@ -324,14 +554,16 @@ impl CPU {
              },
              Jump(cond) => {
                 let should_jump = self.should_jump(cond);
+                // extra cycle to load the new PC when the jump is taken
+                if should_jump { self.internal_cycle(memory) }
                 self.pc = {
                     match instruction.op {
-                        Opcode::JR => self.jump_relative(memory, d8, should_jump),
+                        Opcode::JR => self.jump_relative(d8, should_jump),
                         Opcode::JP => { 
                             if should_jump { a16 }
                             else { self.pc } 
                         },
-                        _ => 0
+                        _ => return Err(unimplemented)
                     }
                 };
             }
@@ -339,6 +571,7 @@ impl CPU {
                 self.pc = self.regfile.get_hl();
             }
             RST(addr) => {
+                self.stack_push(memory, self.pc);
                 self.pc = addr as u16;
             }
             Call(cond) => {
@@ -349,15 +582,22 @@ impl CPU {
                 else { self.pc }
             }
             Return(cond) => {
-                self.pc = if self.should_jump(cond) { self.stack_pop(memory) }
+                // conditional returns take an extra cycle to check the condition
+                if !matches!(cond, JumpCond::Always) { self.internal_cycle(memory) }
+                self.pc = if self.should_jump(cond) { 
+                    let addr = self.stack_pop(memory);
+                    self.internal_cycle(memory);
+                    addr
+                }
                 else { self.pc };
-                if instruction.op == Opcode::RETI { self.scheduled_ime = true }
+                if instruction.op == Opcode::RETI { self.ime = true } // unlike EI, takes effect immediately
             },
             Unary16(target) => {
+                self.internal_cycle(memory);
                 match instruction.op {
                     Opcode::INC => { self.increment16(target) },
                     Opcode::DEC => { self.decrement16(target) },
-                    _ => {}
+                    _ => return Err(unimplemented)
                 }
             }
             Misc => {
@@ -367,47 +607,46 @@ impl CPU {
                         self.ime = false;
                         self.scheduled_ime = false;
                      }
-                    Opcode::HALT => {}
-                    Opcode::STOP => {}
-                    _ => {}
+                    Opcode::HALT => {
+                        let lockup = self.detect_lockups && !self.ime && !memory.interrupts.enabled();
+                        if lockup { return Err(CpuError::Lockup { pc, opcode, prefixed }) }
+                        self.halt(memory)
+                    }
+                    Opcode::STOP => { self.stop(memory) }
+                    Opcode::NOP => {}
+                    _ => return Err(unimplemented)
                 }
             }
             Unsupported => {
-                println!("Unsupported instruction: {:02X?}", instr_byte);
-                return Err("Unsupported instruction");
-            }
-            _ => {
-                println!("Unimplemented instruction: {:02X?}", instr_byte);
-                println!("{}", instruction);
-                return Err("Unimplemented instruction");
+                match self.illegal_opcode_mode {
+                    IllegalOpcodeMode::Error => return Err(CpuError::IllegalOpcode { pc, opcode, prefixed }),
+                    IllegalOpcodeMode::Lock => self.state = CpuState::Locked(opcode),
+                }
             }
+            _ => return Err(unimplemented)
         }
         Ok(instr_byte)
     }
 
     fn check_interrupts(&mut self, memory: &mut Memory) {
-        let interrupts = memory.get_interrupts();
-        memory.clear_interrupts();
-        if interrupts.vblank {
-            self.stack_push(memory, self.pc);
-            self.pc = 0x0040;
-        }
-        else if interrupts.lcd {
-            self.stack_push(memory, self.pc);
-            self.pc = 0x0048;
-        }
-        else if interrupts.timer {
-            self.stack_push(memory, self.pc);
-            self.pc = 0x0050;
-        }
-        else if interrupts.serial {
-            self.stack_push(memory, self.pc);
-            self.pc = 0x0058;
-        }
-        else if interrupts.joypad {
-            self.stack_push(memory, self.pc);
-            self.pc = 0x0060;
-        }
+        if !memory.interrupts.pending() { return }
+        // servicing an interrupt always ends HALT
+        self.state = CpuState::Running;
+        self.ime = false;
+        // dispatch takes 5 cycles: two waits, the two stack writes, and the jump
+        self.internal_cycle(memory);
+        self.internal_cycle(memory);
+        let (most_significant_byte, least_significant_byte) = self.split_u16(self.pc);
+        self.sp_dec();
+        self.write_cycle(memory, self.sp, most_significant_byte);
+        // interrupt is only chosen after the upper byte is pushed, so if that write
+        // cleared the pending bit in IE, dispatch is cancelled and jumps to 0x0000
+        let interrupt = memory.interrupts.highest_pending();
+        if let Some(interrupt) = interrupt { memory.interrupts.acknowledge(interrupt) }
+        self.sp_dec();
+        self.write_cycle(memory, self.sp, least_significant_byte);
+        self.pc = interrupt.map_or(0x0000, |interrupt| interrupt.vector());
+        self.internal_cycle(memory);
     }
 
     // helper functions for instructions
@@ -444,30 +683,32 @@ impl CPU {
 
     fn stack_push(&mut self, memory: &mut Memory, val: u16) {
         let (most_significant_byte, least_significant_byte) = self.split_u16(val);
+        // SP is decremented in an internal cycle before the writes
+        self.internal_cycle(memory);
         // write MSB first
         // println!("pushing - msb: {:02X?} lsb: {:02X?}", most_significant_byte,
         //  least_significant_byte);
         self.sp_dec();
-        memory.write_byte(self.sp, most_significant_byte);
+        self.write_cycle(memory, self.sp, most_significant_byte);
         // println!("At addr: {:02X?}", self.sp);
         self.sp_dec();
-        memory.write_byte(self.sp, least_significant_byte);
+        self.write_cycle(memory, self.sp, least_significant_byte);
         // println!("At addr: {:02X?}", self.sp);
     }
 
     fn stack_pop(&mut self, memory: &mut Memory) -> u16 {
-        let least_significant_byte = memory.read_byte(self.sp) as u16;
+        let least_significant_byte = self.read_cycle(memory, self.sp) as u16;
         self.sp_inc();
-        let most_significant_byte = memory.read_byte(self.sp) as u16;
+        let most_significant_byte = self.read_cycle(memory, self.sp) as u16;
         self.sp_inc();
         (most_significant_byte << 8) | least_significant_byte
     }
 
-    fn jump_relative(&self, memory: &mut Memory, r8: u8, should_jump: bool) -> u16 {
+    fn jump_relative(&self, r8: u8, should_jump: bool) -> u16 {
         if should_jump {
             // converting from two's complement to decimal
             let val: i16 = r8 as i16;
-            let offset : i16 = (-1*(val & 0x80) + (val & 0x7F)) as i16;
+            let offset : i16 = -(val & 0x80) + (val & 0x7F);
             ((self.pc as i16) + offset) as u16
         }
         else {
@@ -525,7 +766,7 @@ impl CPU {
             }
             Word8::HL => {
                 let addr = self.regfile.get_hl();
-                let byte = memory.read_byte(addr);
+                let byte = self.read_cycle(memory, addr);
                 let old_carry = self.regfile.get_carry();
                 self.regfile.set_carry((byte & 0x80) > 0);
                 
@@ -533,7 +774,7 @@ impl CPU {
                     // shift
                     let val = byte.rotate_left(1);
                     let val = val & 0xFE; // bit 0 is always zeroed
-                    memory.write_byte(addr, val);
+                    self.write_cycle(memory, addr, val);
                     self.regfile.set_zero(val == 0);
                 }
                 else {
@@ -544,7 +785,7 @@ impl CPU {
                         let val = byte.rotate_left(1);
                         if old_carry { val | 0x01 } else { val & 0xFE }
                     };
-                    memory.write_byte(addr, val);
+                    self.write_cycle(memory, addr, val);
                     self.regfile.set_zero(val == 0);
                 }
             }
@@ -605,7 +846,7 @@ impl CPU {
             }
             Word8::HL => {
                 let addr = self.regfile.get_hl();
-                let byte = memory.read_byte(addr);
+                let byte = self.read_cycle(memory, addr);
                 let old_carry = self.regfile.get_carry();
                 self.regfile.set_carry((byte & 0x80) > 0);
 
@@ -615,7 +856,7 @@ impl CPU {
                         val & 0x7F
                     }
                     else { val & (byte | 0x7F) };
-                    memory.write_byte(addr, val);
+                    self.write_cycle(memory, addr, val);
                     self.regfile.set_zero(val == 0);
                 }
                 else {
@@ -626,7 +867,7 @@ impl CPU {
                         let val = byte.rotate_right(1);
                         if old_carry { val | 0x80 } else { val & 0x7F }
                     };
-                    memory.write_byte(addr, val);
+                    self.write_cycle(memory, addr, val);
                     self.regfile.set_zero(val == 0);
                 }
             }
@@ -649,20 +890,20 @@ impl CPU {
     h_flag = 0; // h flag is always cleared
      */
     fn daa(&mut self) {
-        if (self.regfile.get_sub()) { // subtraction, only adjust if (half-)carry occurred
-            if (self.regfile.get_carry()) {
+        if self.regfile.get_sub() { // subtraction, only adjust if (half-)carry occurred
+            if self.regfile.get_carry() {
                 self.regfile.r_a = self.regfile.r_a.wrapping_sub(0x60)
             }
-            if (self.regfile.get_half_carry()) {
+            if self.regfile.get_half_carry() {
                 self.regfile.r_a = self.regfile.r_a.wrapping_sub(0x06)
             }
         }
         else { // after an addition, adjust if (half-)carry occurred or if result is out of bounds
-            if (self.regfile.get_carry() || self.regfile.r_a > 0x99) {
+            if self.regfile.get_carry() || self.regfile.r_a > 0x99 {
                 self.regfile.r_a = self.regfile.r_a.wrapping_add(0x60);
                 self.regfile.set_carry(true);
             }
-            if (self.regfile.get_half_carry() || (self.regfile.r_a & 0x0F) > 0x09) {
+            if self.regfile.get_half_carry() || (self.regfile.r_a & 0x0F) > 0x09 {
                 self.regfile.r_a = self.regfile.r_a.wrapping_add(0x06);
             }
         }
@@ -710,9 +951,9 @@ impl CPU {
             },
             Word8::HL => {
                 let addr = self.regfile.get_hl();
-                let byte = memory.read_byte(addr);
+                let byte = self.read_cycle(memory, addr);
                 let val = byte.rotate_left(4);
-                memory.write_byte(addr, val);
+                self.write_cycle(memory, addr, val);
                 self.regfile.set_zero(val == 0);
             },
         }
@@ -733,7 +974,7 @@ impl CPU {
                 Word8::E => self.regfile.r_e,
                 Word8::H => self.regfile.r_h,
                 Word8::L => self.regfile.r_l,
-                Word8::HL => memory.read_byte(self.regfile.get_hl()),
+                Word8::HL => self.read_cycle(memory, self.regfile.get_hl()),
             }
         };
         let bit = val & mask;
@@ -804,12 +1045,12 @@ impl CPU {
             },
             Word8::HL => {
                 let addr = self.regfile.get_hl();
-                let val = memory.read_byte(addr);
+                let val = self.read_cycle(memory, addr);
                 let val = { 
                     if state { val | mask }
                     else { val & !mask }
                 };
-                memory.write_byte(addr, val);
+                self.write_cycle(memory, addr, val);
             },
         }
     }
@@ -946,10 +1187,10 @@ impl CPU {
             }
             ArithmeticArg::HL => {
                 let addr = self.regfile.get_hl();
-                let memory_val = memory.read_byte(addr);
+                let memory_val = self.read_cycle(memory, addr);
                 self.regfile.half_add(memory_val, 1);
                 let inc_val = memory_val.wrapping_add(1);
-                memory.write_byte(addr, inc_val);
+                self.write_cycle(memory, addr, inc_val);
                 self.regfile.set_zero(inc_val == 0);
             }
             _ => {}
@@ -1004,10 +1245,10 @@ impl CPU {
             }
             ArithmeticArg::HL => {
                 let addr = self.regfile.get_hl();
-                let memory_val = memory.read_byte(addr);
+                let memory_val = self.read_cycle(memory, addr);
                 self.regfile.half_sub(memory_val, 1);
                 let dec_val = memory_val.wrapping_sub(1);
-                memory.write_byte(addr, dec_val);
+                self.write_cycle(memory, addr, dec_val);
                 self.regfile.set_zero(dec_val == 0);
             }
             _ => {}
@@ -1,8 +1,10 @@
-use super::{CPU, regfile::Regfile, memory::Memory};
+use super::{CPU, CpuState, Regfile, Memory};
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod cpu_tests {
     use crate::system::CPU;
+    use crate::system::cpu::CpuState;
     use crate::system::Memory;
     use crate::system::cpu::regfile::Regfile;
 
@@ -17,7 +19,8 @@ mod cpu_tests {
         for byte in 0..=0xFF {
             if !undefined_opcodes.contains(&byte) {
                 cpu.pc = 0;
-                memory.write_byte(0, byte);
+                cpu.state = CpuState::Running; // HALT and STOP leave the CPU suspended
+                memory.poke(0, byte);
                 let result = cpu.run(&mut memory);
                 assert_eq!(result, Ok(byte));
             }
@@ -25,6 +28,78 @@ mod cpu_tests {
     }
 
 
+    #[test]
+    fn check_cycles() {
+        // timing emerging from the bus accesses of each instruction should match the opcode table
+        use crate::system::cpu::instruction::Instruction;
+        let undefined_opcodes: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+        // with all flags clear, only the NZ and NC conditions are met
+        let not_taken: [u8; 8] = [0x28, 0x38, 0xC8, 0xCA, 0xCC, 0xD8, 0xDA, 0xDC];
+
+        for byte in 0..=0xFF {
+            if undefined_opcodes.contains(&byte) || byte == 0xCB { continue }
+            let mut cpu = CPU::new();
+            let mut memory = Memory::new();
+            memory.poke(0, byte);
+            cpu.run(&mut memory).unwrap();
+
+            let instruction = Instruction::from_byte(byte, 0);
+            let expected = if not_taken.contains(&byte) { instruction.cycle_len_not_taken() }
+                else { instruction.cycle_len };
+            assert_eq!(cpu.get_cycles(), expected, "opcode {:02X?}", byte);
+        }
+
+        for byte in 0..=0xFF {
+            let mut cpu = CPU::new();
+            let mut memory = Memory::new();
+            memory.poke(0, 0xCB);
+            memory.poke(1, byte);
+            cpu.run(&mut memory).unwrap();
+
+            let instruction = Instruction::from_byte_prefix(byte);
+            assert_eq!(cpu.get_cycles(), instruction.cycle_len, "opcode CB {:02X?}", byte);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_instructions() {
+        // throughput of a NOP/ALU-heavy loop, run with:
+        // cargo test --release bench_instructions -- --ignored --nocapture
+        // when decoding moved from a match on each fetch to the static opcode tables, the median
+        // of five runs went from about 44M to 52M instructions/second, on one noisy machine
+        use std::time::Instant;
+        let mut cpu = CPU::new();
+        let mut memory = Memory::new();
+        let program: [u8; 16] = [
+            0x00, // NOP
+            0x80, // ADD A,B
+            0x3C, // INC A
+            0xA9, // XOR C
+            0x00, // NOP
+            0x91, // SUB C
+            0xA2, // AND D
+            0x04, // INC B
+            0xB3, // OR E
+            0x0D, // DEC C
+            0x00, // NOP
+            0x8F, // ADC A,A
+            0xBC, // CP H
+            0xC3, 0x00, 0x00, // JP 0x0000
+        ];
+        for (addr, byte) in program.iter().enumerate() {
+            memory.poke(addr as u16, *byte);
+        }
+
+        let count: u32 = 20_000_000;
+        let start = Instant::now();
+        for _ in 0..count {
+            cpu.run(&mut memory).unwrap();
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        println!("{} instructions in {:.3}s, {:.0} instructions/second", count, elapsed, count as f64 / elapsed);
+    }
+
     // INS TARGET
     // Instruction Length in Bytes, Cycle Amount
     // Z N H C flag registers
@@ -34,9 +109,9 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let regfile = Regfile::new();
-        memory.write_byte(0, 0x0);
+        memory.poke(0, 0x0);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(1, cpu.pc);
     }
@@ -47,10 +122,10 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x3);
+        memory.poke(0, 0x3);
 
         regfile.set_bc(1); 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
     }
@@ -60,10 +135,10 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0xB);
+        memory.poke(0, 0xB);
 
         regfile.set_bc(0xFFFF); 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
     }
@@ -77,13 +152,13 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x4);
+        memory.poke(0, 0x4);
 
         cpu.regfile.set_sub(true); // verify it gets overwritten
         regfile.set_sub(false);
         regfile.r_b = 1;
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(1, cpu.pc);
 
@@ -92,9 +167,9 @@ mod cpu_tests {
         regfile.r_b = 0;
         regfile.set_half_carry(true);
         regfile.set_zero(true);
-        memory.write_byte(1, 0x4);
+        memory.poke(1, 0x4);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
     }
 
@@ -106,7 +181,7 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x5);
+        memory.poke(0, 0x5);
 
         cpu.regfile.set_sub(false); // verify it gets overwritten
         cpu.regfile.r_b = 1;
@@ -114,7 +189,7 @@ mod cpu_tests {
         regfile.set_zero(true);
         regfile.r_b = 0;
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(1, cpu.pc);
 
@@ -123,9 +198,9 @@ mod cpu_tests {
         regfile.r_b = 0xFF;
         regfile.set_half_carry(true);
         regfile.set_zero(false);
-        memory.write_byte(1, 0x5);
+        memory.poke(1, 0x5);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
     }
 
@@ -137,26 +212,26 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x34);
+        memory.poke(0, 0x34);
 
         cpu.regfile.set_sub(true); // verify it gets overwritten
-        cpu.regfile.set_hl(0x7);
+        cpu.regfile.set_hl(0xC007);
         regfile.set_sub(false);
-        regfile.set_hl(0x7);
+        regfile.set_hl(0xC007);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(1, memory.read_byte(0x7));
+        assert_eq!(1, memory.read_byte(0xC007));
 
         // test half carry and zero
-        memory.write_byte(0x7, 0xFF);
+        memory.write_byte(0xC007, 0xFF);
         regfile.set_half_carry(true);
         regfile.set_zero(true);
-        memory.write_byte(1, 0x34);
+        memory.poke(1, 0x34);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(0, memory.read_byte(0x7))
+        assert_eq!(0, memory.read_byte(0xC007))
     }
 
     #[test]
@@ -167,27 +242,27 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x35);
+        memory.poke(0, 0x35);
 
         cpu.regfile.set_sub(false); // verify it gets overwritten
-        cpu.regfile.set_hl(0x7);
+        cpu.regfile.set_hl(0xC007);
         regfile.set_sub(true);
-        regfile.set_hl(0x7);
-        memory.write_byte(0x7, 2);
+        regfile.set_hl(0xC007);
+        memory.write_byte(0xC007, 2);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(1, memory.read_byte(0x7));
+        assert_eq!(1, memory.read_byte(0xC007));
 
         // test half carry and zero
-        memory.write_byte(0x7, 0x0);
+        memory.write_byte(0xC007, 0x0);
         regfile.set_half_carry(true);
         regfile.set_zero(false);
-        memory.write_byte(1, 0x35);
+        memory.poke(1, 0x35);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(0xFF, memory.read_byte(0x7))
+        assert_eq!(0xFF, memory.read_byte(0xC007))
     }
 
     #[test]
@@ -198,26 +273,26 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x80);
+        memory.poke(0, 0x80);
 
         cpu.regfile.r_b = 7;
         cpu.regfile.set_sub(true);
         regfile.r_a = 7;
         regfile.r_b = 7;
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
 
         cpu.regfile.r_a = 0xFF;
         cpu.regfile.r_b = 1;
-        memory.write_byte(1, 0x80);
+        memory.poke(1, 0x80);
 
         regfile.r_a = 0;
         regfile.r_b = 1;
         regfile.set_zero(true);
         regfile.set_carry(true);
         regfile.set_half_carry(true);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
     }
@@ -230,7 +305,7 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x90);
+        memory.poke(0, 0x90);
 
         cpu.regfile.r_b = 0x0F;
         regfile.r_a = 0xF1;
@@ -238,19 +313,19 @@ mod cpu_tests {
         regfile.set_sub(true);
         regfile.set_half_carry(true);
         regfile.set_carry(true);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
 
         cpu.regfile.r_a = 0xFF;
         cpu.regfile.r_b = 1;
-        memory.write_byte(1, 0x90);
+        memory.poke(1, 0x90);
 
         regfile.r_a = 0xFE;
         regfile.r_b = 1;
         regfile.set_carry(false);
         regfile.set_half_carry(false);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
     }
@@ -263,28 +338,28 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x88);
+        memory.poke(0, 0x88);
 
         cpu.regfile.r_b = 7;
         cpu.regfile.set_carry(false);
         cpu.regfile.set_sub(true);
         regfile.r_a = 7;
         regfile.r_b = 7;
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
 
         cpu.regfile.r_a = 0xFE;
         cpu.regfile.r_b = 1;
         cpu.regfile.set_carry(true);
-        memory.write_byte(1, 0x88);
+        memory.poke(1, 0x88);
 
         regfile.r_a = 0;
         regfile.r_b = 1;
         regfile.set_zero(true);
         regfile.set_carry(true);
         regfile.set_half_carry(true);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
 
         assert_eq!(cpu.regfile, regfile);
     }
@@ -299,16 +374,16 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x02);
+        memory.poke(0, 0x02);
 
-        cpu.regfile.set_bc(0xF);
-        regfile.set_bc(0xF);
+        cpu.regfile.set_bc(0xC00F);
+        regfile.set_bc(0xC00F);
         cpu.regfile.r_a = 7;
         regfile.r_a = 7;
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(7, memory.read_byte(0xF));
+        assert_eq!(7, memory.read_byte(0xC00F));
     }
 
     #[test]
@@ -320,16 +395,16 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x22);
+        memory.poke(0, 0x22);
 
-        cpu.regfile.set_hl(0x0F);
-        regfile.set_hl(0x10);
+        cpu.regfile.set_hl(0xC00F);
+        regfile.set_hl(0xC010);
         cpu.regfile.r_a = 7;
         regfile.r_a = 7;
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(7, memory.read_byte(0xF));
+        assert_eq!(7, memory.read_byte(0xC00F));
     }
 
      // Rotate Opcodes
@@ -341,12 +416,12 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0x07);
+        memory.poke(0, 0x07);
 
         cpu.regfile.r_a = 0x85;
         regfile.r_a = 0x0B;
         regfile.set_carry(true);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
      }
 
@@ -355,13 +430,13 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0xCB);
-        memory.write_byte(1, 0x00);
+        memory.poke(0, 0xCB);
+        memory.poke(1, 0x00);
 
         cpu.regfile.r_b = 0x85;
         regfile.r_b = 0x0B;
         regfile.set_carry(true);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(cpu.pc, 2);
      }
@@ -372,13 +447,13 @@ mod cpu_tests {
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
 
-        memory.write_byte(0, 0x0F);
+        memory.poke(0, 0x0F);
 
         cpu.regfile.r_a = 0x3B;
         regfile.r_a = 0x9D;
         regfile.set_carry(true);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
 
      }
@@ -388,17 +463,17 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0xCB);
-        memory.write_byte(1, 0x1E);
+        memory.poke(0, 0xCB);
+        memory.poke(1, 0x1E);
 
-        memory.write_byte(0x07, 0x81);
-        cpu.regfile.set_hl(0x07);
-        regfile.set_hl(0x07);
+        memory.write_byte(0xC007, 0x81);
+        cpu.regfile.set_hl(0xC007);
+        regfile.set_hl(0xC007);
         regfile.set_carry(true);
 
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
         assert_eq!(cpu.regfile, regfile);
-        assert_eq!(0x40, memory.read_byte(0x07));
+        assert_eq!(0x40, memory.read_byte(0xC007));
         assert_eq!(cpu.pc, 2);
      }
 
@@ -408,20 +483,20 @@ mod cpu_tests {
         let mut cpu = CPU::new();
         let mut memory = Memory::new();
         let mut regfile = Regfile::new();
-        memory.write_byte(0, 0xC5);
-        memory.write_byte(1, 0xD1);
+        memory.poke(0, 0xC5);
+        memory.poke(1, 0xD1);
 
         cpu.regfile.set_bc(0xABCD);
         regfile.set_bc(0xABCD);
 
-        cpu.run(&mut memory); // PUSH BC
+        cpu.run(&mut memory).unwrap(); // PUSH BC
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(cpu.sp, 0xFFFC); // should be decremented by 2
         assert_eq!(0xAB, memory.read_byte(0xFFFD));
         assert_eq!(0xCD, memory.read_byte(0xFFFC));
 
         regfile.set_de(0xABCD);
-        cpu.run(&mut memory); // POP DE
+        cpu.run(&mut memory).unwrap(); // POP DE
         assert_eq!(cpu.regfile, regfile);
         assert_eq!(cpu.sp, 0xFFFE);
     }
@@ -432,21 +507,21 @@ fn JP_0xCA() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
     let mut regfile = Regfile::new();
-    memory.write_byte(0, 0xCA); // JP Z
-    memory.write_byte(1, 0xAB); // msb of address
-    memory.write_byte(2, 0xCD); // lsb of address
+    memory.poke(0, 0xCA); // JP Z
+    memory.poke(1, 0xCD); // lsb of address
+    memory.poke(2, 0xAB); // msb of address
     // jump to 0xABCD if Zero flag is true
     // otherwise advance to line 3 of memory
 
 
 
-    cpu.run(&mut memory);
+    cpu.run(&mut memory).unwrap();
     assert_eq!(cpu.pc, 3);
 
     regfile.set_zero(true);
     cpu.regfile.set_zero(true);
     cpu.pc = 0;
-    cpu.run(&mut memory);
+    cpu.run(&mut memory).unwrap();
 
     assert_eq!(cpu.regfile, regfile);
     assert_eq!(cpu.pc, 0xABCD);
@@ -457,10 +532,10 @@ fn JR_0x30() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
     let mut regfile = Regfile::new();
-    memory.write_byte(0, 0x30); // JR NC
-    memory.write_byte(1, 0x05); // two's complement byte to advance PC by
+    memory.poke(0, 0x30); // JR NC
+    memory.poke(1, 0x05); // two's complement byte to advance PC by
 
-    cpu.run(&mut memory);
+    cpu.run(&mut memory).unwrap();
     assert_eq!(cpu.pc, 0x07); // 0x05 + 0x02
     assert_eq!(cpu.regfile, regfile);
 
@@ -468,7 +543,7 @@ fn JR_0x30() {
     cpu.regfile.set_carry(true);
     cpu.pc = 0;
 
-    cpu.run(&mut memory);
+    cpu.run(&mut memory).unwrap();
     assert_eq!(cpu.regfile, regfile);
     assert_eq!(cpu.pc, 0x02);
 }
@@ -477,14 +552,14 @@ fn JR_0x30() {
 fn CALL_RETURN_0xCC_0xC8() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
-    memory.write_byte(0, 0xCC); // CALL Z
-    memory.write_byte(1, 0xAB);
-    memory.write_byte(2, 0xCD);
-    memory.write_byte(0xABCD, 0xC8);
+    memory.poke(0, 0xCC); // CALL Z
+    memory.poke(1, 0xCD);
+    memory.poke(2, 0xC0);
+    memory.write_byte(0xC0CD, 0xC8);
 
     cpu.regfile.set_zero(false);
 
-    cpu.run(&mut memory); // CALL Z, zero is false
+    cpu.run(&mut memory).unwrap(); // CALL Z, zero is false
     assert_eq!(cpu.pc, 0x3);
     assert_eq!(cpu.sp, 0xFFFE);
     assert_eq!(memory.read_byte(0xFFFE), 0x00);
@@ -493,13 +568,13 @@ fn CALL_RETURN_0xCC_0xC8() {
     cpu.regfile.set_zero(true);
     cpu.pc = 0;
     
-    cpu.run(&mut memory); // CALL Z, zero is true
-    assert_eq!(cpu.pc, 0xABCD);
+    cpu.run(&mut memory).unwrap(); // CALL Z, zero is true
+    assert_eq!(cpu.pc, 0xC0CD);
     assert_eq!(cpu.sp, 0xFFFC);
     assert_eq!(memory.read_byte(0xFFFD), 0x00);
     assert_eq!(memory.read_byte(0xFFFC), 0x03);
 
-    cpu.run(&mut memory); // RET Z, zero is true
+    cpu.run(&mut memory).unwrap(); // RET Z, zero is true
     assert_eq!(cpu.pc, 0x03);
     assert_eq!(cpu.sp, 0xFFFE);
 }
@@ -508,32 +583,343 @@ fn CALL_RETURN_0xCC_0xC8() {
 fn EI_DI_0xFB_0xF4() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
-    memory.write_byte(0, 0xF3); // DI
-    memory.write_byte(1, 0xFB); // EI
-    memory.write_byte(2, 0x00); // just showing there's a NOP here
-    memory.write_byte(3, 0xF3);
-    memory.write_byte(4, 0xFB);
-    memory.write_byte(5, 0xF3);
+    memory.poke(0, 0xF3); // DI
+    memory.poke(1, 0xFB); // EI
+    memory.poke(2, 0x00); // just showing there's a NOP here
+    memory.poke(3, 0xF3);
+    memory.poke(4, 0xFB);
+    memory.poke(5, 0xF3);
 
-    cpu.run(&mut memory); // DI
-    assert_eq!(cpu.ime, false);
+    cpu.run(&mut memory).unwrap(); // DI
+    assert!(!cpu.ime);
 
     // EI doesn't take effect until after next instruction
-    cpu.run(&mut memory); // EI
-    assert_eq!(cpu.ime, false);
-    cpu.run(&mut memory); // NOP
-    assert_eq!(cpu.ime, true);
+    cpu.run(&mut memory).unwrap(); // EI
+    assert!(!cpu.ime);
+    cpu.run(&mut memory).unwrap(); // NOP
+    assert!(cpu.ime);
 
     // if EI is executed, then immediately followed with a DI,
     // then ime is never set to true
-    cpu.run(&mut memory); // DI
-    assert_eq!(cpu.ime, false);
-    cpu.run(&mut memory); // EI
-    assert_eq!(cpu.ime, false);
-    cpu.run(&mut memory); // DI
-    assert_eq!(cpu.ime, false);
+    cpu.run(&mut memory).unwrap(); // DI
+    assert!(!cpu.ime);
+    cpu.run(&mut memory).unwrap(); // EI
+    assert!(!cpu.ime);
+    cpu.run(&mut memory).unwrap(); // DI
+    assert!(!cpu.ime);
 
     
 }
 
+#[test]
+fn HALT_0x76() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0x76); // HALT
+    memory.poke(1, 0x3C); // INC A
+
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Halted);
+    assert_eq!(cpu.pc, 1);
+
+    // no fetching while no interrupt is pending
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Halted);
+    assert_eq!(cpu.pc, 1);
+    assert_eq!(cpu.regfile.r_a, 0);
+
+    // IME is set, so the timer interrupt is serviced on wake
+    memory.write_byte(0xFFFF, 0x04);
+    memory.write_byte(0xFF0F, 0x04);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Running);
+    assert_eq!(cpu.pc, 0x50);
+    assert_eq!(memory.read_byte(0xFFFC), 0x01);
+}
 
+#[test]
+fn HALT_0x76_no_ime() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0xF3); // DI
+    memory.poke(1, 0x76); // HALT
+    memory.poke(2, 0x3C); // INC A
+
+    cpu.run(&mut memory).unwrap(); // DI
+    cpu.run(&mut memory).unwrap(); // HALT
+    assert_eq!(*cpu.get_state(), CpuState::Halted);
+
+    // wakes without servicing the interrupt, continuing after HALT
+    memory.write_byte(0xFFFF, 0x04);
+    memory.write_byte(0xFF0F, 0x04);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Running);
+    assert_eq!(cpu.pc, 2);
+
+    cpu.run(&mut memory).unwrap(); // INC A
+    assert_eq!(cpu.regfile.r_a, 1);
+    assert_eq!(cpu.pc, 3);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE4); // request left pending
+}
+
+#[test]
+fn HALT_bug_0x76() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0xF3); // DI
+    memory.poke(1, 0x76); // HALT
+    memory.poke(2, 0x3E); // LD A,d8
+    memory.poke(3, 0x14); // INC D
+
+    // interrupt already pending when HALT is executed with IME=0
+    memory.write_byte(0xFFFF, 0x04);
+    memory.write_byte(0xFF0F, 0x04);
+
+    cpu.run(&mut memory).unwrap(); // DI
+    cpu.run(&mut memory).unwrap(); // HALT, not entered
+    assert_eq!(*cpu.get_state(), CpuState::Running);
+    assert_eq!(cpu.pc, 2);
+
+    // 0x3E is read twice, so it's loaded as its own operand
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(cpu.regfile.r_a, 0x3E);
+    assert_eq!(cpu.pc, 3);
+
+    cpu.run(&mut memory).unwrap(); // INC D
+    assert_eq!(cpu.regfile.r_d, 1);
+    assert_eq!(cpu.pc, 4);
+}
+
+#[test]
+fn STOP_0x10() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0x200, 0x10); // STOP
+    memory.poke(0x202, 0x3C); // INC A
+
+    // run NOPs until DIV has advanced
+    while cpu.pc < 0x200 {
+        cpu.run(&mut memory).unwrap();
+    }
+    assert_ne!(memory.read_byte(0xFF04), 0);
+
+    cpu.run(&mut memory).unwrap(); // STOP
+    assert_eq!(*cpu.get_state(), CpuState::Stopped);
+    assert_eq!(memory.read_byte(0xFF04), 0);
+    assert_eq!(cpu.pc, 0x202);
+
+    // other interrupts don't wake the CPU from STOP
+    memory.write_byte(0xFFFF, 0x04);
+    memory.write_byte(0xFF0F, 0x04);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Stopped);
+
+    // joypad input
+    memory.write_byte(0xFF0F, 0x10);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Running);
+
+    cpu.run(&mut memory).unwrap(); // INC A
+    assert_eq!(cpu.regfile.r_a, 1);
+}
+
+#[test]
+fn branch_cycles() {
+    // conditional instructions take fewer cycles when not taken
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0x20); // JR NZ
+    memory.poke(2, 0xC2); // JP NZ
+    memory.poke(5, 0xC4); // CALL NZ
+    memory.poke(8, 0xC0); // RET NZ
+    memory.poke(9, 0xC9); // RET
+
+    cpu.regfile.set_zero(true);
+    let not_taken: [u8; 4] = [2, 3, 3, 2];
+    for cycles in not_taken {
+        cpu.run(&mut memory).unwrap();
+        assert_eq!(cpu.get_cycles(), cycles);
+    }
+    assert_eq!(cpu.pc, 9);
+
+    cpu.regfile.set_zero(false);
+    cpu.pc = 0;
+    memory.poke(1, 0x00); // JR NZ to 0x02
+    memory.poke(3, 0x05);
+    memory.poke(4, 0x00); // JP NZ to 0x05
+    memory.poke(6, 0x08);
+    memory.poke(7, 0x00); // CALL NZ to 0x08
+    let taken: [u8; 4] = [3, 4, 6, 5];
+    for cycles in taken {
+        cpu.run(&mut memory).unwrap();
+        assert_eq!(cpu.get_cycles(), cycles);
+    }
+    assert_eq!(cpu.pc, 0x08); // RET NZ returned to the byte after CALL NZ
+
+    // unconditional RET is 4 cycles, one less than a taken RET cc
+    cpu.pc = 9;
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(cpu.get_cycles(), 4);
+}
+
+#[test]
+fn interrupt_dispatch_cycles() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.write_byte(0xFFFF, 0x01);
+    memory.write_byte(0xFF0F, 0x01);
+
+    cpu.run(&mut memory).unwrap(); // NOP, then VBlank dispatch
+    assert_eq!(cpu.pc, 0x40);
+    assert_eq!(cpu.get_cycles(), 1 + 5);
+}
+
+#[test]
+fn interrupt_priority() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.write_byte(0xFFFF, 0x05);
+    memory.write_byte(0xFF0F, 0x05); // VBlank and timer requested together
+
+    cpu.run(&mut memory).unwrap(); // NOP, then VBlank dispatch
+    assert_eq!(cpu.pc, 0x40);
+    assert!(!cpu.ime);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE4); // timer still requested
+
+    memory.poke(0x40, 0xD9); // RETI
+    cpu.run(&mut memory).unwrap(); // RETI, then timer dispatch as IME is set immediately
+    assert_eq!(cpu.pc, 0x50);
+    assert_eq!(cpu.get_cycles(), 4 + 5);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE0);
+}
+
+#[test]
+fn interrupt_ie_push_cancel() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.write_byte(0xFFFF, 0x01);
+    memory.write_byte(0xFF0F, 0x01);
+    cpu.sp = 0x0000;
+    cpu.pc = 0x0200;
+
+    // upper byte of PC is pushed to IE, disabling VBlank before the vector is chosen
+    cpu.run(&mut memory).unwrap(); // NOP, then cancelled dispatch
+    assert_eq!(cpu.pc, 0x0000);
+    assert_eq!(cpu.get_cycles(), 1 + 5);
+    assert_eq!(memory.read_byte(0xFFFF), 0x02);
+    assert_eq!(memory.read_byte(0xFFFE), 0x01);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE1); // not acknowledged
+}
+
+#[test]
+fn cpu_errors() {
+    use super::CpuError;
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0x00); // NOP
+    memory.poke(1, 0xD3); // illegal
+
+    cpu.run(&mut memory).unwrap();
+    let error = cpu.run(&mut memory).unwrap_err();
+    assert_eq!(error, CpuError::IllegalOpcode { pc: 1, opcode: 0xD3, prefixed: false });
+    assert_eq!(error.to_string(), "illegal opcode $D3 at $0001");
+}
+
+#[test]
+fn halt_lockup() {
+    use super::CpuError;
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0, 0xF3); // DI
+    memory.poke(1, 0x76); // HALT
+
+    // without detection, HALT with nothing enabled just halts
+    cpu.run(&mut memory).unwrap(); // DI
+    cpu.run(&mut memory).unwrap(); // HALT
+    assert_eq!(*cpu.get_state(), CpuState::Halted);
+
+    // with IME and IE clear, nothing can wake it
+    cpu.set_lockup_detection(true);
+    cpu.state = CpuState::Running;
+    cpu.pc = 1;
+    let error = cpu.run(&mut memory).unwrap_err();
+    assert_eq!(error, CpuError::Lockup { pc: 1, opcode: 0x76, prefixed: false });
+    assert_eq!(*cpu.get_state(), CpuState::Running);
+
+    // an enabled interrupt can still wake it
+    memory.write_byte(0xFFFF, 0x04);
+    cpu.pc = 1;
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(*cpu.get_state(), CpuState::Halted);
+}
+
+#[test]
+fn illegal_opcode_lock() {
+    use super::IllegalOpcodeMode;
+    let mut cpu = CPU::with_illegal_opcode_mode(IllegalOpcodeMode::Lock);
+    let mut memory = Memory::new();
+    memory.poke(0, 0xFD); // illegal
+    memory.write_byte(0xFFFF, 0x04);
+    memory.write_byte(0xFF0F, 0x04);
+    memory.write_byte(0xFF07, 0x05); // timer enabled at its fastest rate
+
+    assert_eq!(cpu.run(&mut memory), Ok(0xFD));
+    assert_eq!(*cpu.get_state(), CpuState::Locked(0xFD));
+    assert_eq!(cpu.pc, 1); // pending interrupt is ignored
+
+    // no more fetches, but the timer keeps running
+    for _ in 0..8 {
+        assert_eq!(cpu.run(&mut memory), Ok(0xFD));
+        assert_eq!(cpu.get_cycles(), 1);
+    }
+    assert_eq!(cpu.pc, 1);
+    assert_eq!(*cpu.get_state(), CpuState::Locked(0xFD));
+    assert_eq!(memory.read_byte(0xFF05), 2);
+}
+
+#[test]
+fn trace_line() {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    cpu.regfile.set_af(0x01B0);
+    cpu.regfile.set_bc(0x0013);
+    cpu.regfile.set_de(0x00D8);
+    cpu.regfile.set_hl(0x014D);
+    cpu.pc = 0x0100;
+    memory.poke(0x0101, 0xC3); // JP $0150
+    memory.poke(0x0102, 0x50);
+    memory.poke(0x0103, 0x01);
+
+    assert_eq!(cpu.trace_line(&memory),
+        "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,50,01");
+}
+
+#[test]
+fn post_boot_state() {
+    use crate::system::model::Model;
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+    memory.poke(0x014D, 0xE7); // header checksum
+    memory.set_post_boot_state(Model::DMG);
+    cpu.set_post_boot_state(Model::DMG, &memory);
+
+    assert_eq!(cpu.trace_line(&memory),
+        "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,00,00,00");
+    assert!(!cpu.ime);
+    assert_eq!(memory.read_byte(0xFF04), 0xAB); // DIV
+    assert_eq!(memory.read_byte(0xFF40), 0x91); // LCDC
+    assert_eq!(memory.read_byte(0xFF47), 0xFC); // BGP
+    assert_eq!(memory.read_byte(0xFF0F), 0xE1); // IF
+
+    // half carry and carry are only set for a non-zero header checksum
+    memory.poke(0x014D, 0x00);
+    cpu.set_post_boot_state(Model::DMG, &memory);
+    assert_eq!(cpu.regfile.r_f, 0x80);
+
+    memory.set_post_boot_state(Model::CGB);
+    cpu.set_post_boot_state(Model::CGB, &memory);
+    assert_eq!(cpu.regfile.get_af(), 0x1180);
+    assert_eq!(cpu.regfile.get_de(), 0xFF56);
+    assert_eq!(cpu.regfile.get_hl(), 0x000D);
+    assert_eq!(memory.read_byte(0xFF02), 0x7F); // SC
+}
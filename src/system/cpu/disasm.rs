@@ -0,0 +1,262 @@
+use std::fmt;
+
+use crate::system::cpu::instruction::*;
+
+// Disassembler producing RGBDS syntax, using the same opcode tables as the CPU
+
+/// A single disassembled instruction
+pub struct Disassembled {
+    pub addr: u16,
+    pub len: u16,
+    pub text: String,
+}
+
+impl fmt::Display for Disassembled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${:04X}: {}", self.addr, self.text)
+    }
+}
+
+/// Disassembles the instruction at addr in a ROM image mapped from 0x0000
+pub fn disassemble(rom: &[u8], addr: u16) -> Disassembled {
+    let byte = |offset: u16| rom.get(addr.wrapping_add(offset) as usize).copied();
+    let opcode_byte = byte(0).unwrap_or(0);
+    let next_byte = byte(1).unwrap_or(0);
+    let instruction = Instruction::from_byte(opcode_byte, next_byte);
+
+    // instructions cut off by the end of the image are shown as data
+    let complete = (0..instruction.instr_len).all(|offset| byte(offset).is_some());
+    if matches!(instruction.instr_type, InstructionType::Unsupported) || !complete {
+        return Disassembled { addr, len: 1, text: format!("db ${:02X}", opcode_byte) };
+    }
+
+    // immediate words are stored little-endian, as read by CPU::execute
+    let d8 = next_byte;
+    let a16 = ((byte(2).unwrap_or(0) as u16) << 8) | next_byte as u16;
+    let next_addr = addr.wrapping_add(instruction.instr_len);
+    let text = format_instruction(&instruction, d8, a16, next_addr);
+    Disassembled { addr, len: instruction.instr_len, text }
+}
+
+/// Disassembles every instruction starting in start..=end, so the range can reach 0xFFFF
+pub fn disassemble_range(rom: &[u8], start: u16, end: u16) -> Vec<Disassembled> {
+    let mut lines = Vec::new();
+    let mut addr = start as u32;
+    while addr <= end as u32 {
+        let line = disassemble(rom, addr as u16);
+        addr += line.len as u32;
+        lines.push(line);
+    }
+    lines
+}
+
+fn format_instruction(instruction: &Instruction, d8: u8, a16: u16, next_addr: u16) -> String {
+    use crate::system::cpu::instruction::InstructionType::*;
+    let name = mnemonic(instruction.op);
+    match instruction.instr_type {
+        Misc => name.to_string(),
+        Arithmetic(arg) => {
+            match instruction.op {
+                Opcode::DAA | Opcode::CPL | Opcode::SCF | Opcode::CCF => name.to_string(),
+                Opcode::INC | Opcode::DEC => format!("{} {}", name, arithmetic_arg(arg, d8)),
+                _ => format!("{} a, {}", name, arithmetic_arg(arg, d8)),
+            }
+        }
+        Load(target, source) => {
+            // loads through C address the high page, like ldh
+            let name = if matches!(target, LoadTarget::CA) || matches!(source, LoadSource::CA) { "ldh" }
+                else { name };
+            format!("{} {}, {}", name, load_target(target, d8, a16), load_source(source, d8, a16))
+        }
+        Jump(cond) => {
+            let dest = match instruction.op {
+                Opcode::JR => relative_addr(next_addr, d8),
+                _ => a16,
+            };
+            format!("{} {}${:04X}", name, jump_cond(cond), dest)
+        }
+        JumpHL => "jp hl".to_string(),
+        Push(pair) | Pop(pair) => format!("{} {}", name, register_pair(pair)),
+        Call(cond) => format!("{} {}${:04X}", name, jump_cond(cond), a16),
+        Return(JumpCond::Always) => name.to_string(),
+        Return(cond) => format!("{} {}", name, jump_cond(cond).trim_end_matches(", ")),
+        RST(addr) => format!("rst ${:02X}", addr),
+        Arithmetic16(pair) => format!("{} {}", name, register_pair(pair)),
+        Unary16(word) => format!("{} {}", name, word16(word)),
+        Load16(word) => format!("ld {}, ${:04X}", word16(word), a16),
+        LoadMemory16 => format!("ld [${:04X}], sp", a16),
+        Add16(word) => format!("add hl, {}", word16(word)),
+        AddSP => format!("add sp, {}", signed(d8)),
+        Rotate(word) => {
+            match instruction.op {
+                Opcode::RLCA | Opcode::RLA | Opcode::RRCA | Opcode::RRA => name.to_string(),
+                _ => format!("{} {}", name, word8(word)),
+            }
+        }
+        Bit(word, bit) => format!("{} {}, {}", name, bit, word8(word)),
+        LoadHL => {
+            let offset = d8 as i8;
+            let sign = if offset < 0 { '-' } else { '+' };
+            format!("ld hl, sp{}${:02X}", sign, offset.unsigned_abs())
+        }
+        LoadSP => "ld sp, hl".to_string(),
+        Unsupported => "db".to_string(),
+    }
+}
+
+fn mnemonic(op: Opcode) -> &'static str {
+    match op {
+        Opcode::DAA => "daa",
+        Opcode::CCF => "ccf",
+        Opcode::SCF => "scf",
+        Opcode::CPL => "cpl",
+        Opcode::NOP => "nop",
+        Opcode::ADD => "add",
+        Opcode::ADC => "adc",
+        Opcode::INC => "inc",
+        Opcode::SUB => "sub",
+        Opcode::SBC => "sbc",
+        Opcode::DEC => "dec",
+        Opcode::AND => "and",
+        Opcode::OR => "or",
+        Opcode::XOR => "xor",
+        Opcode::CP => "cp",
+        Opcode::JP => "jp",
+        Opcode::JR => "jr",
+        Opcode::LD | Opcode::LDHL | Opcode::LDSP => "ld",
+        Opcode::LDH => "ldh",
+        Opcode::PUSH => "push",
+        Opcode::POP => "pop",
+        Opcode::CALL => "call",
+        Opcode::RET => "ret",
+        Opcode::RETI => "reti",
+        Opcode::RST => "rst",
+        Opcode::RLCA => "rlca",
+        Opcode::RLA => "rla",
+        Opcode::RRCA => "rrca",
+        Opcode::RRA => "rra",
+        Opcode::RLC => "rlc",
+        Opcode::RRC => "rrc",
+        Opcode::RL => "rl",
+        Opcode::RR => "rr",
+        Opcode::SLA => "sla",
+        Opcode::SRA => "sra",
+        Opcode::SRL => "srl",
+        Opcode::SWAP => "swap",
+        Opcode::BIT => "bit",
+        Opcode::RES => "res",
+        Opcode::SET => "set",
+        Opcode::DI => "di",
+        Opcode::EI => "ei",
+        Opcode::STOP => "stop",
+        Opcode::HALT => "halt",
+        Opcode::NULL => "db",
+    }
+}
+
+fn relative_addr(next_addr: u16, r8: u8) -> u16 {
+    next_addr.wrapping_add(r8 as i8 as u16)
+}
+
+fn signed(byte: u8) -> String {
+    let val = byte as i8;
+    if val < 0 { format!("-${:02X}", val.unsigned_abs()) }
+    else { format!("${:02X}", val) }
+}
+
+fn jump_cond(cond: JumpCond) -> &'static str {
+    match cond {
+        JumpCond::Zero => "z, ",
+        JumpCond::NotZero => "nz, ",
+        JumpCond::Carry => "c, ",
+        JumpCond::NotCarry => "nc, ",
+        JumpCond::Always => "",
+    }
+}
+
+fn arithmetic_arg(arg: ArithmeticArg, d8: u8) -> String {
+    match arg {
+        ArithmeticArg::A => "a".to_string(),
+        ArithmeticArg::B => "b".to_string(),
+        ArithmeticArg::C => "c".to_string(),
+        ArithmeticArg::D => "d".to_string(),
+        ArithmeticArg::E => "e".to_string(),
+        ArithmeticArg::H => "h".to_string(),
+        ArithmeticArg::L => "l".to_string(),
+        ArithmeticArg::HL => "[hl]".to_string(),
+        ArithmeticArg::D8 => format!("${:02X}", d8),
+        ArithmeticArg::None => String::new(),
+    }
+}
+
+fn word8(word: Word8) -> &'static str {
+    match word {
+        Word8::A => "a",
+        Word8::B => "b",
+        Word8::C => "c",
+        Word8::D => "d",
+        Word8::E => "e",
+        Word8::H => "h",
+        Word8::L => "l",
+        Word8::HL => "[hl]",
+    }
+}
+
+fn word16(word: Word16) -> &'static str {
+    match word {
+        Word16::BC => "bc",
+        Word16::DE => "de",
+        Word16::HL => "hl",
+        Word16::SP => "sp",
+    }
+}
+
+fn register_pair(pair: RegisterPair) -> &'static str {
+    match pair {
+        RegisterPair::BC => "bc",
+        RegisterPair::DE => "de",
+        RegisterPair::HL => "hl",
+        RegisterPair::AF => "af",
+    }
+}
+
+fn load_target(target: LoadTarget, d8: u8, a16: u16) -> String {
+    match target {
+        LoadTarget::A => "a".to_string(),
+        LoadTarget::B => "b".to_string(),
+        LoadTarget::C => "c".to_string(),
+        LoadTarget::D => "d".to_string(),
+        LoadTarget::E => "e".to_string(),
+        LoadTarget::H => "h".to_string(),
+        LoadTarget::L => "l".to_string(),
+        LoadTarget::HL => "[hl]".to_string(),
+        LoadTarget::HLI => "[hl+]".to_string(),
+        LoadTarget::HLD => "[hl-]".to_string(),
+        LoadTarget::BC => "[bc]".to_string(),
+        LoadTarget::DE => "[de]".to_string(),
+        LoadTarget::A8 => format!("[${:04X}]", 0xFF00 | d8 as u16),
+        LoadTarget::A16 => format!("[${:04X}]", a16),
+        LoadTarget::CA => "[c]".to_string(),
+    }
+}
+
+fn load_source(source: LoadSource, d8: u8, a16: u16) -> String {
+    match source {
+        LoadSource::A => "a".to_string(),
+        LoadSource::B => "b".to_string(),
+        LoadSource::C => "c".to_string(),
+        LoadSource::D => "d".to_string(),
+        LoadSource::E => "e".to_string(),
+        LoadSource::H => "h".to_string(),
+        LoadSource::L => "l".to_string(),
+        LoadSource::HL => "[hl]".to_string(),
+        LoadSource::HLI => "[hl+]".to_string(),
+        LoadSource::HLD => "[hl-]".to_string(),
+        LoadSource::BC => "[bc]".to_string(),
+        LoadSource::DE => "[de]".to_string(),
+        LoadSource::D8 => format!("${:02X}", d8),
+        LoadSource::A8 => format!("[${:04X}]", 0xFF00 | d8 as u16),
+        LoadSource::A16 => format!("[${:04X}]", a16),
+        LoadSource::CA => "[c]".to_string(),
+    }
+}
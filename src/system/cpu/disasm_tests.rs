@@ -0,0 +1,85 @@
+use super::disasm::{disassemble, disassemble_range};
+
+#[test]
+fn disasm_load() {
+    // immediate words are stored least significant byte first
+    let rom = [0xFA, 0x44, 0xFF];
+    let line = disassemble(&rom, 0);
+    assert_eq!(line.text, "ld a, [$FF44]");
+    assert_eq!(line.len, 3);
+
+    let rom = [0xF0, 0x44, 0xE2, 0x22, 0x3E, 0x12, 0x08, 0x00, 0xC0];
+    assert_eq!(disassemble(&rom, 0).text, "ldh a, [$FF44]");
+    assert_eq!(disassemble(&rom, 2).text, "ldh [c], a");
+    assert_eq!(disassemble(&rom, 3).text, "ld [hl+], a");
+    assert_eq!(disassemble(&rom, 4).text, "ld a, $12");
+    assert_eq!(disassemble(&rom, 6).text, "ld [$C000], sp");
+}
+
+#[test]
+fn disasm_jump() {
+    let mut rom = [0u8; 0x200];
+    rom[0x160] = 0x20; // JR NZ
+    rom[0x161] = 0xEE; // -18
+    let line = disassemble(&rom, 0x160);
+    assert_eq!(line.text, "jr nz, $0150");
+    assert_eq!(line.len, 2);
+
+    let rom = [0xC3, 0x50, 0x01, 0xCC, 0x34, 0x12, 0xC0, 0xD9, 0xFF, 0xE9];
+    assert_eq!(disassemble(&rom, 0).text, "jp $0150");
+    assert_eq!(disassemble(&rom, 3).text, "call z, $1234");
+    assert_eq!(disassemble(&rom, 6).text, "ret nz");
+    assert_eq!(disassemble(&rom, 7).text, "reti");
+    assert_eq!(disassemble(&rom, 8).text, "rst $38");
+    assert_eq!(disassemble(&rom, 9).text, "jp hl");
+}
+
+#[test]
+fn disasm_arithmetic() {
+    let rom = [0x80, 0xCE, 0x01, 0x34, 0x27, 0x03, 0x39, 0xE8, 0x05, 0xF8, 0xFE, 0x07, 0xF5];
+    assert_eq!(disassemble(&rom, 0).text, "add a, b");
+    assert_eq!(disassemble(&rom, 1).text, "adc a, $01");
+    assert_eq!(disassemble(&rom, 3).text, "inc [hl]");
+    assert_eq!(disassemble(&rom, 4).text, "daa");
+    assert_eq!(disassemble(&rom, 5).text, "inc bc");
+    assert_eq!(disassemble(&rom, 6).text, "add hl, sp");
+    assert_eq!(disassemble(&rom, 7).text, "add sp, $05");
+    assert_eq!(disassemble(&rom, 9).text, "ld hl, sp-$02");
+    assert_eq!(disassemble(&rom, 11).text, "rlca");
+    assert_eq!(disassemble(&rom, 12).text, "push af");
+}
+
+#[test]
+fn disasm_prefix() {
+    let rom = [0xCB, 0x7C, 0xCB, 0x86, 0xCB, 0x37];
+    let line = disassemble(&rom, 0);
+    assert_eq!(line.text, "bit 7, h");
+    assert_eq!(line.len, 2);
+    assert_eq!(disassemble(&rom, 2).text, "res 0, [hl]");
+    assert_eq!(disassemble(&rom, 4).text, "swap a");
+}
+
+#[test]
+fn disasm_range() {
+    let rom = [0x00, 0x3E, 0x12, 0xC3, 0x50, 0x01, 0xD3, 0x76, 0x3E];
+    let lines: Vec<String> = disassemble_range(&rom, 0, rom.len() as u16 - 1)
+        .iter().map(|line| line.to_string()).collect();
+    assert_eq!(lines, [
+        "$0000: nop",
+        "$0001: ld a, $12",
+        "$0003: jp $0150",
+        "$0006: db $D3", // illegal opcode
+        "$0007: halt",
+        "$0008: db $3E", // cut off by the end of the image
+    ]);
+}
+
+#[test]
+fn disasm_range_end() {
+    // the end is inclusive, so the last address of the address space can be reached
+    let mut rom = vec![0x00; 0x10000];
+    rom[0xFFFF] = 0x76;
+    let lines = disassemble_range(&rom, 0xFFFE, 0xFFFF);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].to_string(), "$FFFF: halt");
+}
@@ -2,6 +2,7 @@ use std::fmt;
 
 // Following are definitions used for CPU instructions
 
+#[derive(Clone, Copy)]
 pub struct Instruction {
     pub op: Opcode,
     pub instr_type: InstructionType,
@@ -9,7 +10,7 @@ pub struct Instruction {
     pub cycle_len: u8
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Opcode {
     DAA,
     CCF,
@@ -60,7 +61,7 @@ pub enum Opcode {
     NULL // for debugging purposes
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum InstructionType {
     Misc,
     Arithmetic(ArithmeticArg),
@@ -85,37 +86,37 @@ pub enum InstructionType {
     Unsupported
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum ArithmeticArg {
     A, B, C, D, E, H, L, HL, D8, None
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum Word8 {
     A, B, C, D, E, H, L, HL
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum LoadTarget {
     A, B, C, D, E, H, L, HL, HLI, HLD, BC, DE, A8, A16, CA
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum LoadSource {
     A, B, C, D, E, H, L, HL, HLI, HLD, BC, DE, D8, A8, A16, CA
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum RegisterPair {
     BC, DE, HL, AF
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum Word16 {
     BC, DE, HL, SP
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum JumpCond {
     Zero,
     NotZero,
@@ -124,15 +125,58 @@ pub enum JumpCond {
     Always,
 }
 
+/// Base opcodes, indexed by opcode byte
+static OPCODES: [Instruction; 256] = build_table(false);
+/// Opcodes prefixed with 0xCB, indexed by the byte following the prefix
+static PREFIX_OPCODES: [Instruction; 256] = build_table(true);
+
+const fn build_table(prefix: bool) -> [Instruction; 256] {
+    let mut table = [Instruction {
+        op: Opcode::NULL,
+        instr_type: InstructionType::Unsupported,
+        instr_len: 1,
+        cycle_len: 0
+    }; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        table[byte] = if prefix { Instruction::decode_prefix(byte as u8) }
+            else { Instruction::decode(byte as u8) };
+        byte += 1;
+    }
+    table
+}
+
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Opcode: {:?}\n", self.op)?;
+        writeln!(f, "Opcode: {:?}", self.op)?;
         write!(f, "Instruction Type: {:?}", self.instr_type)
     }
 }
 
 impl Instruction {
+    /// Cycle count when a conditional JR, JP, CALL or RET is not taken,
+    /// since `cycle_len` holds the cost of taking the branch
+    pub fn cycle_len_not_taken(&self) -> u8 {
+        match (&self.instr_type, &self.op) {
+            (InstructionType::Jump(_), Opcode::JR) => 2,
+            (InstructionType::Jump(_), Opcode::JP) => 3,
+            (InstructionType::Call(_), _) => 3,
+            (InstructionType::Return(_), _) => 2,
+            _ => self.cycle_len
+        }
+    }
+
     pub fn from_byte(byte: u8, next_byte: u8) -> Instruction {
+        // instructions are looked up in tables decoded at compile time
+        if byte == 0xCB { PREFIX_OPCODES[next_byte as usize] }
+        else { OPCODES[byte as usize] }
+    }
+
+    pub fn from_byte_prefix(byte: u8) -> Instruction {
+        PREFIX_OPCODES[byte as usize]
+    }
+
+    const fn decode(byte: u8) -> Instruction {
         // raw bytes are matched to yield the correctly formatted instruction
         match byte {
             0x00 => Instruction { 
@@ -1360,8 +1404,13 @@ impl Instruction {
                     instr_len: 3,
                     cycle_len: 4 
                 },
-            // 0xCB reserved for denoting prefix instructions
-            0xCB => Self::from_byte_prefix(next_byte),
+            // 0xCB reserved for denoting prefix instructions, decoded by decode_prefix
+            0xCB => Instruction {
+                    op: Opcode::NULL,
+                    instr_type: InstructionType::Unsupported,
+                    instr_len: 2,
+                    cycle_len: 2
+                },
             0xCC => Instruction { 
                     op: Opcode::CALL, 
                     instr_type: InstructionType::Call(JumpCond::Zero),
@@ -1436,7 +1485,7 @@ impl Instruction {
                 },
             0xD9 => Instruction { 
                     op: Opcode::RETI, 
-                    instr_type: InstructionType::Return(JumpCond::NotZero),
+                    instr_type: InstructionType::Return(JumpCond::Always),
                     instr_len: 1,
                     cycle_len: 4 
                 },
@@ -1545,7 +1594,7 @@ impl Instruction {
             0xF2 => Instruction { 
                     op: Opcode::LD, 
                     instr_type: InstructionType::Load(LoadTarget::A, LoadSource::CA),
-                    instr_len: 2,
+                    instr_len: 1,
                     cycle_len: 2 
                 },
             0xF3 => Instruction {
@@ -1576,13 +1625,13 @@ impl Instruction {
                     op: Opcode::LDHL,
                     instr_type: InstructionType::LoadHL,
                     instr_len: 2,
-                    cycle_len: 4
+                    cycle_len: 3
                 },
             0xF9 => Instruction {
                     op: Opcode::LDSP,
                     instr_type: InstructionType::LoadSP,
                     instr_len: 1,
-                    cycle_len: 4
+                    cycle_len: 2
                 },
             0xFA => Instruction {
                     op: Opcode::LD,
@@ -1608,11 +1657,12 @@ impl Instruction {
                     instr_len: 1,
                     cycle_len: 4
                 },
+             // undefined opcodes, which hang the CPU after being fetched
              _ => Instruction { 
                     op: Opcode::NULL, 
                     instr_type: InstructionType::Unsupported,
                     instr_len: 1,
-                    cycle_len: 0 
+                    cycle_len: 1 
                 },
         }
     }
@@ -1621,8 +1671,8 @@ impl Instruction {
 // Prefix Instructions:
 // ==================================*
 
-    pub fn from_byte_prefix(byte: u8) -> Instruction {
-        // called instead of normal from_byte if instruction is prefixed
+    const fn decode_prefix(byte: u8) -> Instruction {
+        // called instead of normal decode if instruction is prefixed
         // with 0xCB
         match byte {
             0x00 => Instruction { 
@@ -2049,7 +2099,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 0),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x47 => Instruction { 
                     op: Opcode::BIT, 
@@ -2097,7 +2147,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 1),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x4F => Instruction { 
                     op: Opcode::BIT, 
@@ -2145,7 +2195,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 2),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x57 => Instruction { 
                     op: Opcode::BIT, 
@@ -2193,7 +2243,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 3),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x5F => Instruction { 
                     op: Opcode::BIT, 
@@ -2241,7 +2291,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 4),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x67 => Instruction { 
                     op: Opcode::BIT, 
@@ -2289,7 +2339,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 5),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x6F => Instruction { 
                     op: Opcode::BIT, 
@@ -2337,7 +2387,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 6),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x77 => Instruction { 
                     op: Opcode::BIT, 
@@ -2385,7 +2435,7 @@ impl Instruction {
                     op: Opcode::BIT, 
                     instr_type: InstructionType::Bit(Word8::HL, 7),
                     instr_len: 2,
-                    cycle_len: 4 
+                    cycle_len: 3 
                 },
             0x7F => Instruction { 
                     op: Opcode::BIT, 
@@ -3161,12 +3211,6 @@ impl Instruction {
                     instr_len: 2,
                     cycle_len: 2 
                 },
-             _ => Instruction { 
-                    op: Opcode::NULL, 
-                    instr_type: InstructionType::Unsupported,
-                    instr_len: 1,
-                    cycle_len: 1 
-                },
         }
     }
 }
@@ -85,39 +85,39 @@ impl Regfile {
     // four upper bits corresponding to a different flag
 
     pub fn get_zero(&self) -> bool {
-        if (self.r_f & 0x80) > 0 { true } else { false }
+        (self.r_f & 0x80) > 0
     }
 
     pub fn set_zero(&mut self, val: bool) {
-        if val { self.r_f = self.r_f | 0x80 }
-        else { self.r_f = self.r_f & 0x7F }
+        if val { self.r_f |= 0x80 }
+        else { self.r_f &= 0x7F }
     }
 
     pub fn get_sub(&self) -> bool {
-        if (self.r_f & 0x40) > 0 { true } else { false }
+        (self.r_f & 0x40) > 0
     }
 
     pub fn set_sub(&mut self, val: bool) {
-        if val { self.r_f = self.r_f | 0x40 }
-        else { self.r_f = self.r_f & 0xBF }
+        if val { self.r_f |= 0x40 }
+        else { self.r_f &= 0xBF }
     }
 
     pub fn get_half_carry(&self) -> bool {
-        if (self.r_f & 0x20) > 0 { true } else { false }
+        (self.r_f & 0x20) > 0
     }
 
     pub fn set_half_carry(&mut self, val: bool) {
-        if val { self.r_f = self.r_f | 0x20 }
-        else { self.r_f = self.r_f & 0xDF }
+        if val { self.r_f |= 0x20 }
+        else { self.r_f &= 0xDF }
     }
 
     pub fn get_carry(&self) -> bool {
-        if (self.r_f & 0x10) > 0 { true } else { false }
+        (self.r_f & 0x10) > 0
     }
 
     pub fn set_carry(&mut self, val: bool) {
-        if val { self.r_f = self.r_f | 0x10 }
-        else { self.r_f = self.r_f & 0xEF }
+        if val { self.r_f |= 0x10 }
+        else { self.r_f &= 0xEF }
     }
 
     pub fn toggle_carry(&mut self) {
@@ -0,0 +1,102 @@
+/// Interrupt sources, in order of priority
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interrupt {
+    VBlank,
+    Lcd,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    const PRIORITY: [Interrupt; 5] = [Interrupt::VBlank, Interrupt::Lcd, Interrupt::Timer, Interrupt::Serial, Interrupt::Joypad];
+
+    /// Bit in IE and IF
+    pub fn bit(&self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0x01,
+            Interrupt::Lcd => 0x02,
+            Interrupt::Timer => 0x04,
+            Interrupt::Serial => 0x08,
+            Interrupt::Joypad => 0x10,
+        }
+    }
+
+    /// Address the CPU jumps to when servicing the interrupt
+    pub fn vector(&self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x0040,
+            Interrupt::Lcd => 0x0048,
+            Interrupt::Timer => 0x0050,
+            Interrupt::Serial => 0x0058,
+            Interrupt::Joypad => 0x0060,
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub struct InterruptController {
+    r_IE: u8,
+    r_IF: u8, // only the low 5 bits are implemented
+}
+
+// memory mapped registers
+// FF0F - IF: Interrupt Flag
+// FFFF - IE: Interrupt Enable
+
+#[allow(non_snake_case)]
+impl InterruptController {
+    pub fn new() -> InterruptController {
+        InterruptController {
+            r_IE: 0,
+            r_IF: 0,
+        }
+    }
+
+    pub fn get_IE(&self) -> u8 {
+        self.r_IE
+    }
+
+    pub fn set_IE(&mut self, val: u8) {
+        self.r_IE = val;
+    }
+
+    pub fn get_IF(&self) -> u8 {
+        // unused upper bits always read as 1
+        0xE0 | self.r_IF
+    }
+
+    pub fn set_IF(&mut self, val: u8) {
+        self.r_IF = val & 0x1F;
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.r_IF |= interrupt.bit();
+    }
+
+    /// Clears the request of an interrupt being serviced, leaving any others pending
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.r_IF &= !interrupt.bit();
+    }
+
+    /// True if any enabled interrupt is requested, regardless of IME
+    pub fn pending(&self) -> bool {
+        (self.r_IE & self.r_IF & 0x1F) > 0
+    }
+
+    pub fn enabled(&self) -> bool {
+        (self.r_IE & 0x1F) > 0
+    }
+
+    /// Joypad request in IF, ignoring IE since STOP wakes on
+    /// button input even when the joypad interrupt is disabled
+    pub fn joypad_requested(&self) -> bool {
+        (self.r_IF & Interrupt::Joypad.bit()) > 0
+    }
+
+    /// Enabled and requested interrupt with the highest priority
+    pub fn highest_pending(&self) -> Option<Interrupt> {
+        let pending = self.r_IE & self.r_IF;
+        Interrupt::PRIORITY.into_iter().find(|interrupt| pending & interrupt.bit() > 0)
+    }
+}
@@ -0,0 +1,32 @@
+use super::{Interrupt, InterruptController};
+
+#[test]
+fn interrupt_registers() {
+    let mut interrupts = InterruptController::new();
+    interrupts.set_IF(0xFF);
+    assert_eq!(interrupts.get_IF(), 0xFF);
+    interrupts.set_IF(0x00);
+    assert_eq!(interrupts.get_IF(), 0xE0); // upper bits read as 1
+
+    interrupts.set_IE(0xFF);
+    assert_eq!(interrupts.get_IE(), 0xFF);
+    assert!(!interrupts.pending());
+}
+
+#[test]
+fn interrupt_acknowledge() {
+    let mut interrupts = InterruptController::new();
+    interrupts.set_IE(0x1E);
+    interrupts.request(Interrupt::Joypad);
+    interrupts.request(Interrupt::Timer);
+    interrupts.request(Interrupt::VBlank); // requested, but not enabled
+    assert_eq!(interrupts.highest_pending(), Some(Interrupt::Timer));
+
+    interrupts.acknowledge(Interrupt::Timer);
+    assert_eq!(interrupts.get_IF(), 0xF1);
+    assert_eq!(interrupts.highest_pending(), Some(Interrupt::Joypad));
+
+    interrupts.acknowledge(Interrupt::Joypad);
+    assert_eq!(interrupts.highest_pending(), None);
+    assert!(!interrupts.pending());
+}
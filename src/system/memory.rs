@@ -1,87 +1,153 @@
+pub mod io;
+pub mod ram;
+#[cfg(test)]
+pub mod memory_tests;
+
 use crate::system::*;
+use crate::system::memory::io::{IoRegisters, unused_bits};
+use crate::system::memory::ram::Ram;
 
+/// Memory bus, dispatching each access to the region or device mapped at its address
 pub struct Memory {
-    memory: [u8; 0x10000],
+    pub cartridge: Cartridge, // 0x0000-0x7FFF, 0xA000-0xBFFF
+    wram: Ram, // 0xC000-0xDFFF, mirrored at 0xE000-0xFDFF
+    io: IoRegisters, // 0xFF00-0xFF7F
+    hram: Ram, // 0xFF80-0xFFFE
     // devices mapped to memory addresses
-    pub timer: Timer
-}
-
-pub struct Interrupts {
-    pub vblank: bool,
-    pub lcd: bool,
-    pub timer: bool,
-    pub serial: bool,
-    pub joypad: bool,
+    pub timer: Timer,
+    pub ppu: Ppu, // also holds VRAM, 0x8000-0x9FFF, and OAM, 0xFE00-0xFE9F
+    pub interrupts: InterruptController,
+    boot_rom: Option<BootRom>, // unmapped by writing to 0xFF50
 }
 
 impl Memory {
     pub fn new() -> Memory {
         Memory {
-            memory: [0 as u8; 0x10000],
-            timer: Timer::new()
+            cartridge: Cartridge::empty(),
+            wram: Ram::new(0x2000),
+            io: IoRegisters::new(),
+            hram: Ram::new(0x7F),
+            timer: Timer::new(),
+            ppu: Ppu::new(),
+            interrupts: InterruptController::new(),
+            boot_rom: None,
+        }
+    }
+
+    pub fn map_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    /// IO registers as left by the boot ROM of the given model
+    pub fn set_post_boot_state(&mut self, model: Model) {
+        for &(addr, byte) in POST_BOOT_IO.iter().chain(model.post_boot_io_overrides()) {
+            self.write_byte(addr, byte);
         }
+        self.timer.set_DIV(model.post_boot_div());
     }
 
     pub fn read_byte(&self, addr: u16) -> u8 {
+        if let Some(byte) = self.boot_rom.as_ref().and_then(|boot_rom| boot_rom.read(addr)) {
+            return byte;
+        }
         match addr {
+            0x0000..=0x7FFF => self.cartridge.read_rom(addr),
+            0x8000..=0x9FFF => self.ppu.read_vram(addr),
+            0xA000..=0xBFFF => self.cartridge.read_ram(addr),
+            0xC000..=0xDFFF => self.wram.read(addr - 0xC000),
+            0xE000..=0xFDFF => self.wram.read(addr - 0xE000), // echo RAM
+            0xFE00..=0xFE9F => self.ppu.read_oam(addr),
+            0xFEA0..=0xFEFF => 0x00, // unusable
+
             // Timer Registers
             0xFF04 => self.timer.get_DIV(),
             0xFF05 => self.timer.get_TIMA(),
             0xFF06 => self.timer.get_TMA(),
-            0xFF07 => self.timer.get_TAC(),
+            0xFF07 => unused_bits(addr) | self.timer.get_TAC(),
 
-            // normal unmapped address
-            _ => self.memory[addr as usize]
-        }
-    }
+            // LCD Registers
+            0xFF40 => self.ppu.get_LCDC(),
+            0xFF41 => self.ppu.get_STAT(),
+            0xFF42 => self.ppu.get_SCY(),
+            0xFF43 => self.ppu.get_SCX(),
+            0xFF44 => self.ppu.get_LY(),
+            0xFF45 => self.ppu.get_LYC(),
+            0xFF47 => self.ppu.get_BGP(),
+            0xFF48 => self.ppu.get_OBP0(),
+            0xFF49 => self.ppu.get_OBP1(),
+            0xFF4A => self.ppu.get_WY(),
+            0xFF4B => self.ppu.get_WX(),
+
+            // Interrupt Registers
+            0xFF0F => self.interrupts.get_IF(),
+            0xFFFF => self.interrupts.get_IE(),
 
-    pub fn read_next_word(&self, addr: u16) -> u16 {
-        let least_significant_byte = self.memory[(addr+2) as usize] as u16;
-        let most_significant_byte = self.memory[(addr+1) as usize] as u16;
-        (most_significant_byte << 8) | least_significant_byte
+            0xFF00..=0xFF7F => self.io.read(addr),
+            0xFF80..=0xFFFE => self.hram.read(addr - 0xFF80),
+        }
     }
 
     pub fn write_byte(&mut self, addr: u16, byte: u8) {
         match addr {
+            0x0000..=0x7FFF => self.cartridge.write_rom(addr, byte),
+            0x8000..=0x9FFF => self.ppu.write_vram(addr, byte),
+            0xA000..=0xBFFF => self.cartridge.write_ram(addr, byte),
+            0xC000..=0xDFFF => self.wram.write(addr - 0xC000, byte),
+            0xE000..=0xFDFF => self.wram.write(addr - 0xE000, byte), // echo RAM
+            0xFE00..=0xFE9F => self.ppu.write_oam(addr, byte),
+            0xFEA0..=0xFEFF => {} // unusable
+
             // Timer Registers
             0xFF04 => self.timer.reset_DIV(),
             0xFF05 => self.timer.set_TIMA(byte),
             0xFF06 => self.timer.set_TMA(byte),
             0xFF07 => self.timer.set_TAC(byte),
 
-            // normal unmapped address
-            _ => self.memory[addr as usize] = byte
-        }
-    }
+            // LCD Registers
+            0xFF40 => self.ppu.set_LCDC(byte, &mut self.interrupts),
+            0xFF41 => self.ppu.set_STAT(byte, &mut self.interrupts),
+            0xFF42 => self.ppu.set_SCY(byte),
+            0xFF43 => self.ppu.set_SCX(byte),
+            0xFF44 => {} // LY is read-only
+            0xFF45 => self.ppu.set_LYC(byte, &mut self.interrupts),
+            0xFF47 => self.ppu.set_BGP(byte),
+            0xFF48 => self.ppu.set_OBP0(byte),
+            0xFF49 => self.ppu.set_OBP1(byte),
+            0xFF4A => self.ppu.set_WY(byte),
+            0xFF4B => self.ppu.set_WX(byte),
 
-    pub fn get_interrupts(&self) -> Interrupts {
-        // 0xFFFF - Interrupt Enable
-        // 0xFF0F - Interrupt Flags
-        // Flags in 0xFF0F:
-        // Bit 0 - VBlank
-        // Bit 1 - LCD STAT
-        // Bit 2 - Timer
-        // Bit 3 - Serial
-        // Bit 4 - Joypad
-        let enable = self.memory[0xFFFF];
-        let flags = self.memory[0xFF0F];
-        let vblank = ((enable & 0x1) & (flags & 0x1)) > 0;
-        let lcd = ((enable & 0x2) & (flags & 0x2)) > 0;
-        let timer = ((enable & 0x4) & (flags & 0x4)) > 0;
-        let serial = ((enable & 0x8) & (flags & 0x8)) > 0;
-        let joypad = ((enable & 0x10) & (flags & 0x10)) > 0;
-        Interrupts {
-            vblank, lcd, timer, serial, joypad
-        }
+            // Interrupt Registers
+            0xFF0F => self.interrupts.set_IF(byte),
+            0xFFFF => self.interrupts.set_IE(byte),
+
+            // Boot ROM is unmapped until the next reset
+            0xFF50 => {
+                if byte & 0x1 > 0 { self.boot_rom = None }
+                self.io.write(addr, byte)
+            }
 
+            0xFF00..=0xFF7F => self.io.write(addr, byte),
+            0xFF80..=0xFFFE => self.hram.write(addr - 0xFF80, byte),
+        }
     }
 
-    pub fn clear_interrupts(&mut self) {
-        self.memory[0xFF0F] = 0;
+    /// Writes a byte as a debugger would, patching the cartridge ROM
+    /// where the bus would pass the write to the cartridge instead
+    pub fn poke(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x7FFF => self.cartridge.patch_rom(addr, byte),
+            _ => self.write_byte(addr, byte),
+        }
     }
 
     pub fn update_cycle(&mut self, cycles: u8) {
+        self.cartridge.update_cycle(cycles);
         let timer = self.timer.update_timestep(cycles);
-        if timer { self.memory[0xFF0F] |= 0x04 }
+        if timer { self.interrupts.request(Interrupt::Timer) }
+        self.ppu.update_cycle(cycles, &mut self.interrupts);
     }
 }
\ No newline at end of file
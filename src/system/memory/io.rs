@@ -0,0 +1,42 @@
+/// IO registers at 0xFF00-0xFF7F without a dedicated device, stored as written.
+/// Bits not implemented by hardware read back as 1.
+pub struct IoRegisters {
+    data: [u8; 0x80],
+}
+
+impl IoRegisters {
+    pub fn new() -> IoRegisters {
+        IoRegisters { data: [0; 0x80] }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        self.data[(addr & 0x7F) as usize] | unused_bits(addr)
+    }
+
+    pub fn write(&mut self, addr: u16, byte: u8) {
+        self.data[(addr & 0x7F) as usize] = byte;
+    }
+}
+
+/// Bits of each register that always read as 1, with unmapped registers reading 0xFF
+pub const fn unused_bits(addr: u16) -> u8 {
+    match addr {
+        0xFF00 => 0xCF, // P1, with no buttons pressed on the input lines
+        0xFF01 => 0x00, // SB
+        0xFF02 => 0x7E, // SC
+        0xFF07 => 0xF8, // TAC
+        0xFF0F => 0xE0, // IF
+        0xFF10 => 0x80, // NR10
+        0xFF11 | 0xFF16 => 0x3F, // NR11, NR21, length is write-only
+        0xFF12 | 0xFF17 | 0xFF21 => 0x00, // NR12, NR22, NR42
+        0xFF14 | 0xFF19 | 0xFF1E | 0xFF23 => 0xBF, // NRx4, only length enable is readable
+        0xFF1A => 0x7F, // NR30
+        0xFF1C => 0x9F, // NR32
+        0xFF22 | 0xFF24 | 0xFF25 => 0x00, // NR43, NR50, NR51
+        0xFF26 => 0x70, // NR52
+        0xFF30..=0xFF3F => 0x00, // wave RAM
+        0xFF41 => 0x80, // STAT
+        0xFF40 | 0xFF42..=0xFF4B => 0x00, // LCD registers
+        _ => 0xFF,
+    }
+}
@@ -0,0 +1,70 @@
+use super::Memory;
+use super::io::IoRegisters;
+use super::ram::Ram;
+use crate::system::cartridge::Cartridge;
+
+#[test]
+fn ram_region() {
+    let mut ram = Ram::new(0x7F);
+    ram.write(0x00, 0x12);
+    ram.write(0x7E, 0x34);
+    assert_eq!(ram.read(0x00), 0x12);
+    assert_eq!(ram.read(0x7E), 0x34);
+}
+
+#[test]
+fn io_unused_bits() {
+    let mut io = IoRegisters::new();
+    io.write(0xFF00, 0x20);
+    assert_eq!(io.read(0xFF00), 0xEF); // P1, select lines kept and buttons released
+    io.write(0xFF02, 0x00);
+    assert_eq!(io.read(0xFF02), 0x7E); // SC
+    io.write(0xFF41, 0x00);
+    assert_eq!(io.read(0xFF41), 0x80); // STAT
+    io.write(0xFF42, 0x5A);
+    assert_eq!(io.read(0xFF42), 0x5A); // SCY, fully implemented
+    io.write(0xFF03, 0x00);
+    assert_eq!(io.read(0xFF03), 0xFF); // unmapped
+}
+
+#[test]
+fn cartridge_rom_read_only() {
+    let mut rom = vec![0; 0x4000];
+    rom[0x0150] = 0x3C;
+    let mut memory = Memory::new();
+    memory.cartridge = Cartridge::new(rom);
+
+    memory.write_byte(0x0150, 0x00);
+    assert_eq!(memory.read_byte(0x0150), 0x3C);
+    assert_eq!(memory.read_byte(0x7FFF), 0xFF); // past the end of a short image
+    assert_eq!(memory.read_byte(0xA000), 0xFF); // no cartridge RAM
+}
+
+#[test]
+fn echo_ram() {
+    let mut memory = Memory::new();
+    memory.write_byte(0xC000, 0x11);
+    assert_eq!(memory.read_byte(0xE000), 0x11);
+    memory.write_byte(0xFDFF, 0x22);
+    assert_eq!(memory.read_byte(0xDDFF), 0x22);
+}
+
+#[test]
+fn unusable_area() {
+    let mut memory = Memory::new();
+    memory.write_byte(0xFEA0, 0x12);
+    assert_eq!(memory.read_byte(0xFEA0), 0x00);
+    memory.write_byte(0xFE9F, 0x34); // end of OAM
+    assert_eq!(memory.read_byte(0xFE9F), 0x34);
+}
+
+#[test]
+fn regions() {
+    let mut memory = Memory::new();
+    for (addr, byte) in [(0x8000, 0x01), (0x9FFF, 0x02), (0xC000, 0x03), (0xDFFF, 0x04),
+        (0xFE00, 0x05), (0xFF80, 0x06), (0xFFFE, 0x07), (0xFFFF, 0x08)] {
+        memory.write_byte(addr, byte);
+        assert_eq!(memory.read_byte(addr), byte, "address {:04X?}", addr);
+    }
+    assert_eq!(memory.read_byte(0xFF07), 0xF8); // TAC upper bits
+}
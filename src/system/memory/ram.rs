@@ -0,0 +1,18 @@
+/// Block of RAM, addressed by offset from the start of its region
+pub struct Ram {
+    data: Box<[u8]>,
+}
+
+impl Ram {
+    pub fn new(len: usize) -> Ram {
+        Ram { data: vec![0; len].into_boxed_slice() }
+    }
+
+    pub fn read(&self, offset: u16) -> u8 {
+        self.data[offset as usize]
+    }
+
+    pub fn write(&mut self, offset: u16, byte: u8) {
+        self.data[offset as usize] = byte;
+    }
+}
@@ -0,0 +1,83 @@
+/// Game Boy hardware revisions, which differ in the state their boot ROMs leave behind
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Model {
+    DMG0, // early original Game Boy
+    DMG, // original Game Boy
+    MGB, // Game Boy Pocket
+    SGB, // Super Game Boy
+    SGB2, // Super Game Boy 2
+    CGB, // Game Boy Color, running a color cartridge
+}
+
+/// IO registers as left by the boot ROM, excluding DIV which is set through the timer
+pub const POST_BOOT_IO: [(u16, u8); 27] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF01, 0x00), // SB
+    (0xFF02, 0x7E), // SC
+    (0xFF0F, 0xE1), // IF
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF26, 0xF1), // NR52
+    (0xFF40, 0x91), // LCDC
+    (0xFF41, 0x85), // STAT
+    (0xFF46, 0xFF), // DMA
+    (0xFF47, 0xFC), // BGP
+    (0xFFFF, 0x00), // IE
+];
+
+impl Model {
+    /// A, B, C, D, E, H and L at 0x0100. F is given by post_boot_flags.
+    pub fn post_boot_registers(&self) -> [u8; 7] {
+        match self {
+            Model::DMG0 => [0x01, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+            Model::DMG => [0x01, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::MGB => [0xFF, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::SGB => [0x01, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+            Model::SGB2 => [0xFF, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+            Model::CGB => [0x11, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        }
+    }
+
+    /// F at 0x0100, which on DMG and MGB depends on the cartridge header checksum at 0x014D
+    pub fn post_boot_flags(&self, header_checksum: u8) -> u8 {
+        match self {
+            Model::DMG | Model::MGB if header_checksum == 0 => 0x80,
+            Model::DMG | Model::MGB => 0xB0,
+            Model::CGB => 0x80,
+            Model::DMG0 | Model::SGB | Model::SGB2 => 0x00,
+        }
+    }
+
+    /// DIV at 0x0100, which on the later models varies with the time the boot ROM takes
+    pub fn post_boot_div(&self) -> u8 {
+        match self {
+            Model::DMG0 => 0x18,
+            Model::DMG | Model::MGB => 0xAB,
+            Model::SGB | Model::SGB2 | Model::CGB => 0x00,
+        }
+    }
+
+    /// IO registers that differ from POST_BOOT_IO on this model
+    pub fn post_boot_io_overrides(&self) -> &'static [(u16, u8)] {
+        match self {
+            Model::SGB | Model::SGB2 => &[(0xFF26, 0xF0)], // NR52
+            Model::CGB => &[(0xFF02, 0x7F), (0xFF46, 0x00)], // SC, DMA
+            _ => &[],
+        }
+    }
+}
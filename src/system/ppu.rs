@@ -0,0 +1,382 @@
+use crate::system::interrupts::{Interrupt, InterruptController};
+use crate::system::memory::ram::Ram;
+
+/// Pixels in each row of the framebuffer
+pub const SCREEN_WIDTH: usize = 160;
+/// Rows in the framebuffer
+pub const SCREEN_HEIGHT: usize = 144;
+
+/// Dots in a scanline, 4 per M-cycle
+pub const DOTS_PER_LINE: u16 = 456;
+/// Scanlines in a frame, the last 10 in VBlank
+pub const LINES: u8 = 154;
+/// Visible scanlines, after which VBlank starts
+pub const VISIBLE_LINES: u8 = 144;
+
+const OAM_SCAN_DOTS: u16 = 80;
+const OAM_OBJECTS: u16 = 40;
+const OBJECTS_PER_LINE: usize = 10;
+const DRAWING_DOTS: u16 = 172; // shortest mode 3, without scrolling, window or objects
+
+// dots in an M-cycle, as passed to update_cycle
+const CYCLE_LEN: u16 = 4;
+
+/// PPU mode, as read from the low bits of STAT
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PpuMode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+#[allow(non_snake_case)]
+pub struct Ppu {
+    vram: Ram, // 0x8000-0x9FFF
+    oam: Ram, // 0xFE00-0xFE9F, 4 bytes for each of 40 objects
+    framebuffer: Box<[u8]>, // shades 0-3, SCREEN_WIDTH x SCREEN_HEIGHT, filled a line at a time
+    // internal values
+    dot: u16, // dots into the current line
+    mode: PpuMode,
+    stat_line: bool, // OR of the enabled STAT sources, interrupting on its rising edge
+    window_triggered: bool, // LY has matched WY this frame, so the window can be drawn
+    window_line: u8, // line of the window to draw next, only advanced on lines showing it
+    // memory mapped values
+    r_LCDC: u8,
+    r_STAT: u8, // only the source enables in bits 3-6 are stored
+    r_SCY: u8,
+    r_SCX: u8,
+    r_LY: u8,
+    r_LYC: u8,
+    r_BGP: u8,
+    r_OBP0: u8,
+    r_OBP1: u8,
+    r_WY: u8,
+    r_WX: u8,
+}
+
+
+// memory mapped registers
+// FF40 - LCDC: LCD Control
+// FF41 - STAT: LCD Status
+// FF42 - SCY: Background Scroll Y
+// FF43 - SCX: Background Scroll X
+// FF44 - LY: LCD Y Coordinate, read-only
+// FF45 - LYC: LY Compare
+// FF47 - BGP: Background Palette
+// FF48 - OBP0: Object Palette 0
+// FF49 - OBP1: Object Palette 1
+// FF4A - WY: Window Y Position
+// FF4B - WX: Window X Position plus 7
+
+#[allow(non_snake_case)]
+impl Ppu {
+    pub fn new() -> Ppu {
+        Ppu {
+            vram: Ram::new(0x2000),
+            oam: Ram::new(0xA0),
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+            dot: 0,
+            mode: PpuMode::HBlank,
+            stat_line: false,
+            window_triggered: false,
+            window_line: 0,
+            r_LCDC: 0,
+            r_STAT: 0,
+            r_SCY: 0,
+            r_SCX: 0,
+            r_LY: 0,
+            r_LYC: 0,
+            r_BGP: 0,
+            r_OBP0: 0,
+            r_OBP1: 0,
+            r_WY: 0,
+            r_WX: 0,
+        }
+    }
+
+    pub fn lcd_enabled(&self) -> bool {
+        self.r_LCDC & 0x80 > 0 // bit 7 of LCDC set
+    }
+
+    pub fn get_mode(&self) -> PpuMode {
+        self.mode
+    }
+
+    pub fn read_vram(&self, addr: u16) -> u8 {
+        self.vram.read(addr - 0x8000)
+    }
+
+    pub fn write_vram(&mut self, addr: u16, byte: u8) {
+        self.vram.write(addr - 0x8000, byte);
+    }
+
+    pub fn read_oam(&self, addr: u16) -> u8 {
+        self.oam.read(addr - 0xFE00)
+    }
+
+    pub fn write_oam(&mut self, addr: u16, byte: u8) {
+        self.oam.write(addr - 0xFE00, byte);
+    }
+
+    /// Shades 0 (lightest) to 3 (darkest) of each pixel, row by row
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    /// Advances the PPU by the given number of M-cycles, requesting VBlank and STAT interrupts
+    pub fn update_cycle(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        if !self.lcd_enabled() { return }
+        for _ in 0..cycles as u16 * CYCLE_LEN {
+            self.step_dot(interrupts);
+        }
+    }
+
+    fn step_dot(&mut self, interrupts: &mut InterruptController) {
+        self.dot += 1;
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.r_LY = (self.r_LY + 1) % LINES;
+        }
+        let mode = match (self.r_LY, self.dot) {
+            (line, _) if line >= VISIBLE_LINES => PpuMode::VBlank,
+            (_, dot) if dot < OAM_SCAN_DOTS => PpuMode::OamScan,
+            (_, dot) if dot < OAM_SCAN_DOTS + DRAWING_DOTS => PpuMode::Drawing,
+            _ => PpuMode::HBlank,
+        };
+        if mode != self.mode {
+            self.mode = mode;
+            match mode {
+                // the line is drawn all at once, with the registers as they are at the end of mode 3
+                PpuMode::HBlank => self.render_line(),
+                PpuMode::VBlank => {
+                    interrupts.request(Interrupt::VBlank);
+                    self.window_triggered = false;
+                    self.window_line = 0;
+                }
+                _ => {}
+            }
+        }
+        self.update_stat_line(interrupts);
+    }
+
+    /// Requests the STAT interrupt if any enabled source has just become active.
+    /// While one source holds the line high, others can't raise another interrupt.
+    fn update_stat_line(&mut self, interrupts: &mut InterruptController) {
+        let line = self.lcd_enabled() && (
+            (self.r_STAT & 0x08 > 0 && self.mode == PpuMode::HBlank)
+            || (self.r_STAT & 0x10 > 0 && self.mode == PpuMode::VBlank)
+            || (self.r_STAT & 0x20 > 0 && self.mode == PpuMode::OamScan)
+            // the mode 2 source also fires at the start of VBlank
+            || (self.r_STAT & 0x20 > 0 && self.r_LY == VISIBLE_LINES && self.dot == 0)
+            || (self.r_STAT & 0x40 > 0 && self.coincidence()));
+        if line && !self.stat_line { interrupts.request(Interrupt::Lcd) }
+        self.stat_line = line;
+    }
+
+    fn render_line(&mut self) {
+        let ly = self.r_LY;
+        if ly == self.r_WY { self.window_triggered = true }
+        let row = ly as usize * SCREEN_WIDTH;
+
+        // colors before palette mapping, which decide whether objects behind the background show
+        let mut colors = [0; SCREEN_WIDTH];
+        // on DMG, LCDC bit 0 blanks the background and window
+        let background = self.r_LCDC & 0x01 > 0;
+        if background { self.render_background(ly, &mut colors) }
+        for (x, color) in colors.iter().enumerate() {
+            self.framebuffer[row + x] = if background { (self.r_BGP >> (color * 2)) & 0x03 } else { 0 };
+        }
+        if self.r_LCDC & 0x02 > 0 { self.render_objects(ly, &colors) }
+    }
+
+    fn render_background(&mut self, ly: u8, colors: &mut [u8; SCREEN_WIDTH]) {
+        // WX is the window's left edge plus 7
+        let window_x = self.r_WX as i16 - 7;
+        let window = self.r_LCDC & 0x20 > 0 && self.window_triggered && window_x < SCREEN_WIDTH as i16;
+
+        for (x, color) in colors.iter_mut().enumerate() {
+            *color = if window && x as i16 >= window_x {
+                let map = if self.r_LCDC & 0x40 > 0 { 0x9C00 } else { 0x9800 };
+                self.tile_pixel(map, (x as i16 - window_x) as u8, self.window_line)
+            }
+            else {
+                let map = if self.r_LCDC & 0x08 > 0 { 0x9C00 } else { 0x9800 };
+                // the 256x256 background wraps around
+                self.tile_pixel(map, self.r_SCX.wrapping_add(x as u8), self.r_SCY.wrapping_add(ly))
+            };
+        }
+        if window { self.window_line += 1 }
+    }
+
+    /// Indexes of the objects on a line, in OAM order, as found by the OAM scan.
+    /// Objects off the left or right edge still count towards the limit.
+    fn scan_oam(&self, ly: u8, height: u8) -> Vec<u16> {
+        // Y is the object's top edge plus 16
+        let top = ly as u16 + 16;
+        (0..OAM_OBJECTS)
+            .filter(|index| {
+                let y = self.oam.read(index * 4) as u16;
+                y <= top && top < y + height as u16
+            })
+            .take(OBJECTS_PER_LINE)
+            .collect()
+    }
+
+    fn render_objects(&mut self, ly: u8, colors: &[u8; SCREEN_WIDTH]) {
+        let height = if self.r_LCDC & 0x04 > 0 { 16 } else { 8 };
+        let mut objects = self.scan_oam(ly, height);
+        // on DMG, the object with the lower X is drawn on top, then the one earlier in OAM
+        objects.sort_by_key(|index| (self.oam.read(index * 4 + 1), *index));
+
+        let row = ly as usize * SCREEN_WIDTH;
+        for (x, color) in colors.iter().enumerate() {
+            for index in &objects {
+                let [y, left, tile, attributes] = [0, 1, 2, 3].map(|byte| self.oam.read(index * 4 + byte));
+                // X is the object's left edge plus 8
+                let column = x as i16 + 8 - left as i16;
+                if !(0..8).contains(&column) { continue }
+                let column = if attributes & 0x20 > 0 { 7 - column } else { column } as u16;
+                let line = ly.wrapping_add(16).wrapping_sub(y);
+                let line = if attributes & 0x40 > 0 { height - 1 - line } else { line } as u16;
+                // 8x16 objects use an even tile on top and the following odd tile below it
+                let tile = if height == 16 { tile & 0xFE } else { tile } as u16;
+
+                let line_addr = 0x8000 + tile * 16 + line * 2;
+                let (low, high) = (self.read_vram(line_addr), self.read_vram(line_addr + 1));
+                let bit = 7 - column;
+                let object_color = (((high >> bit) & 0x01) << 1) | ((low >> bit) & 0x01);
+                // color 0 is transparent, showing objects underneath
+                if object_color == 0 { continue }
+                // the priority bit puts the object behind background colors 1-3
+                if attributes & 0x80 == 0 || *color == 0 {
+                    let palette = if attributes & 0x10 > 0 { self.r_OBP1 } else { self.r_OBP0 };
+                    self.framebuffer[row + x] = (palette >> (object_color * 2)) & 0x03;
+                }
+                break;
+            }
+        }
+    }
+
+    /// Color 0-3 of a pixel in a 32x32 tile map
+    fn tile_pixel(&self, map: u16, x: u8, y: u8) -> u8 {
+        let tile = self.read_vram(map + (y as u16 / 8) * 32 + x as u16 / 8);
+        // 0x8000 addressing uses unsigned tile numbers, 0x8800 addressing signed from 0x9000
+        let tile_addr = if self.r_LCDC & 0x10 > 0 {
+            0x8000 + tile as u16 * 16
+        }
+        else {
+            0x9000u16.wrapping_add((tile as i8 as i16 * 16) as u16)
+        };
+        let line_addr = tile_addr + (y as u16 % 8) * 2;
+        let (low, high) = (self.read_vram(line_addr), self.read_vram(line_addr + 1));
+        let bit = 7 - x % 8;
+        (((high >> bit) & 0x01) << 1) | ((low >> bit) & 0x01)
+    }
+
+    fn coincidence(&self) -> bool {
+        self.r_LY == self.r_LYC
+    }
+
+    pub fn get_LCDC(&self) -> u8 {
+        self.r_LCDC
+    }
+
+    // switching the LCD off resets LY and the mode, and it restarts from the top of the frame
+    pub fn set_LCDC(&mut self, val: u8, interrupts: &mut InterruptController) {
+        let was_enabled = self.lcd_enabled();
+        self.r_LCDC = val;
+        if was_enabled && !self.lcd_enabled() {
+            self.dot = 0;
+            self.r_LY = 0;
+            self.mode = PpuMode::HBlank;
+            self.window_triggered = false;
+            self.window_line = 0;
+            // the screen goes blank while switched off
+            self.framebuffer.fill(0);
+        }
+        else if !was_enabled && self.lcd_enabled() {
+            self.mode = PpuMode::OamScan;
+        }
+        self.update_stat_line(interrupts);
+    }
+
+    pub fn get_STAT(&self) -> u8 {
+        0x80 | self.r_STAT | ((self.coincidence() as u8) << 2) | self.mode as u8
+    }
+
+    // mode and coincidence bits are read-only
+    pub fn set_STAT(&mut self, val: u8, interrupts: &mut InterruptController) {
+        self.r_STAT = val & 0x78;
+        self.update_stat_line(interrupts);
+    }
+
+    pub fn get_SCY(&self) -> u8 {
+        self.r_SCY
+    }
+
+    pub fn set_SCY(&mut self, val: u8) {
+        self.r_SCY = val;
+    }
+
+    pub fn get_SCX(&self) -> u8 {
+        self.r_SCX
+    }
+
+    pub fn set_SCX(&mut self, val: u8) {
+        self.r_SCX = val;
+    }
+
+    pub fn get_LY(&self) -> u8 {
+        self.r_LY
+    }
+
+    pub fn get_LYC(&self) -> u8 {
+        self.r_LYC
+    }
+
+    pub fn set_LYC(&mut self, val: u8, interrupts: &mut InterruptController) {
+        self.r_LYC = val;
+        self.update_stat_line(interrupts);
+    }
+
+    pub fn get_BGP(&self) -> u8 {
+        self.r_BGP
+    }
+
+    pub fn set_BGP(&mut self, val: u8) {
+        self.r_BGP = val;
+    }
+
+    pub fn get_OBP0(&self) -> u8 {
+        self.r_OBP0
+    }
+
+    pub fn set_OBP0(&mut self, val: u8) {
+        self.r_OBP0 = val;
+    }
+
+    pub fn get_OBP1(&self) -> u8 {
+        self.r_OBP1
+    }
+
+    pub fn set_OBP1(&mut self, val: u8) {
+        self.r_OBP1 = val;
+    }
+
+    pub fn get_WY(&self) -> u8 {
+        self.r_WY
+    }
+
+    pub fn set_WY(&mut self, val: u8) {
+        self.r_WY = val;
+    }
+
+    pub fn get_WX(&self) -> u8 {
+        self.r_WX
+    }
+
+    pub fn set_WX(&mut self, val: u8) {
+        self.r_WX = val;
+    }
+}
@@ -0,0 +1,369 @@
+use super::{InterruptController, Memory, Ppu};
+use super::ppu::{PpuMode, SCREEN_WIDTH};
+
+// M-cycles in a scanline
+const LINE_CYCLES: u8 = 114;
+
+fn enabled_ppu(interrupts: &mut InterruptController) -> Ppu {
+    let mut ppu = Ppu::new();
+    ppu.set_LCDC(0x80, interrupts);
+    ppu
+}
+
+#[test]
+fn ppu_modes() {
+    let mut interrupts = InterruptController::new();
+    let mut ppu = enabled_ppu(&mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+
+    // 80 dots of OAM scan, 172 of drawing, then HBlank to the end of the 456 dot line
+    ppu.update_cycle(19, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+    ppu.update_cycle(1, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::Drawing);
+    ppu.update_cycle(42, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::Drawing);
+    ppu.update_cycle(1, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::HBlank);
+    assert_eq!(ppu.get_STAT() & 0x03, 0x00);
+    ppu.update_cycle(50, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 0);
+    ppu.update_cycle(1, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 1);
+    assert_eq!(ppu.get_STAT() & 0x03, 0x02);
+
+    // VBlank from line 144 to 153, then back to line 0
+    for _ in 1..144 { ppu.update_cycle(LINE_CYCLES, &mut interrupts) }
+    assert_eq!(ppu.get_LY(), 144);
+    assert_eq!(ppu.get_mode(), PpuMode::VBlank);
+    assert_eq!(interrupts.get_IF(), 0xE1);
+    for _ in 144..153 { ppu.update_cycle(LINE_CYCLES, &mut interrupts) }
+    assert_eq!(ppu.get_LY(), 153);
+    ppu.update_cycle(LINE_CYCLES, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 0);
+    assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+}
+
+#[test]
+fn ppu_lcd_off() {
+    let mut interrupts = InterruptController::new();
+    let mut ppu = enabled_ppu(&mut interrupts);
+    for _ in 0..10 { ppu.update_cycle(LINE_CYCLES, &mut interrupts) }
+    assert_eq!(ppu.get_LY(), 10);
+
+    // LY and the mode are reset, and nothing advances
+    ppu.set_LCDC(0x00, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 0);
+    assert_eq!(ppu.get_STAT() & 0x03, 0x00);
+    ppu.update_cycle(LINE_CYCLES, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 0);
+}
+
+#[test]
+fn ppu_lyc() {
+    let mut memory = Memory::new();
+    memory.write_byte(0xFF45, 0x02); // LYC
+    memory.write_byte(0xFF41, 0x40); // LYC=LY source
+    memory.write_byte(0xFF40, 0x80);
+    assert_eq!(memory.read_byte(0xFF41), 0xC2);
+
+    memory.update_cycle(LINE_CYCLES);
+    memory.update_cycle(LINE_CYCLES);
+    assert_eq!(memory.read_byte(0xFF44), 0x02);
+    assert_eq!(memory.read_byte(0xFF41), 0xC6); // coincidence flag
+    assert_eq!(memory.read_byte(0xFF0F), 0xE2);
+
+    // LY can't be written, and LYC writes compare immediately
+    memory.write_byte(0xFF0F, 0x00);
+    memory.write_byte(0xFF44, 0x00);
+    assert_eq!(memory.read_byte(0xFF44), 0x02);
+    memory.write_byte(0xFF45, 0x00);
+    assert_eq!(memory.read_byte(0xFF41) & 0x04, 0x00);
+    memory.write_byte(0xFF45, 0x02);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE2);
+}
+
+#[test]
+fn ppu_stat_blocking() {
+    let mut interrupts = InterruptController::new();
+    let mut ppu = enabled_ppu(&mut interrupts);
+    ppu.set_STAT(0x28, &mut interrupts); // HBlank and OAM scan sources
+    assert_eq!(interrupts.get_IF(), 0xE2); // OAM scan already active
+
+    // the line drops during drawing, so HBlank raises it again
+    interrupts.set_IF(0x00);
+    ppu.update_cycle(63, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::HBlank);
+    assert_eq!(interrupts.get_IF(), 0xE2);
+    interrupts.set_IF(0x00);
+    ppu.update_cycle(51, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+    // HBlank held the line high into OAM scan, so there's no new edge
+    assert_eq!(interrupts.get_IF(), 0xE0);
+
+    // with LYC=LY also holding the line through drawing, HBlank doesn't interrupt
+    ppu.set_LYC(1, &mut interrupts);
+    ppu.set_STAT(0x48, &mut interrupts);
+    ppu.update_cycle(63, &mut interrupts);
+    assert_eq!(ppu.get_mode(), PpuMode::HBlank);
+    assert_eq!(interrupts.get_IF(), 0xE0);
+}
+
+#[test]
+fn ppu_vblank_stat() {
+    let mut interrupts = InterruptController::new();
+    let mut ppu = enabled_ppu(&mut interrupts);
+    ppu.set_STAT(0x20, &mut interrupts); // OAM scan source
+    for _ in 0..143 { ppu.update_cycle(LINE_CYCLES, &mut interrupts) }
+    ppu.update_cycle(63, &mut interrupts);
+    interrupts.set_IF(0x00);
+
+    // the OAM scan source also fires as VBlank starts
+    ppu.update_cycle(51, &mut interrupts);
+    assert_eq!(ppu.get_LY(), 144);
+    assert_eq!(interrupts.get_IF(), 0xE3);
+}
+
+/// Memory with tile 1 at both 0x8010 and 0x9010, and tile 0x81 at 0x8810,
+/// each with a different pattern of colors on its first row
+fn tile_memory() -> Memory {
+    let mut memory = Memory::new();
+    // low bits then high bits of each row, leftmost pixel in bit 7
+    for (addr, colors) in [(0x8010, [0xFF, 0x00]), (0x9010, [0x00, 0xFF]), (0x8810, [0xFF, 0xFF])] {
+        for row in 0..8 {
+            memory.write_byte(addr + row * 2, colors[0]);
+            memory.write_byte(addr + row * 2 + 1, colors[1]);
+        }
+    }
+    memory.write_byte(0xFF47, 0xE4); // BGP, colors map to the same shades
+    memory
+}
+
+/// Runs until the given line has next been drawn, with the LCD already on
+fn run_to_line(memory: &mut Memory, line: u8) {
+    memory.update_cycle(1);
+    while memory.read_byte(0xFF44) != line || memory.ppu.get_mode() != PpuMode::HBlank {
+        memory.update_cycle(1);
+    }
+}
+
+fn line(memory: &Memory, line: usize) -> &[u8] {
+    &memory.ppu.framebuffer()[line * SCREEN_WIDTH..(line + 1) * SCREEN_WIDTH]
+}
+
+#[test]
+fn ppu_background() {
+    let mut memory = tile_memory();
+    memory.write_byte(0x9800, 0x01);
+    memory.write_byte(0x9802, 0x01);
+    memory.write_byte(0x9C01, 0x01);
+
+    // 0x8000 tile data
+    memory.write_byte(0xFF40, 0x91);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..24], [[1; 8], [0; 8], [1; 8]].concat());
+
+    // 0x8800 tile data, where tile 1 is at 0x9010, and 0x81 at 0x8810
+    memory.write_byte(0x9802, 0x81);
+    memory.write_byte(0xFF40, 0x81);
+    run_to_line(&mut memory, 1);
+    assert_eq!(line(&memory, 1)[0..24], [[2; 8], [0; 8], [3; 8]].concat());
+
+    // the other map
+    memory.write_byte(0xFF40, 0x99);
+    run_to_line(&mut memory, 2);
+    assert_eq!(line(&memory, 2)[0..24], [[0; 8], [1; 8], [0; 8]].concat());
+
+    // palette mapping, with color 1 drawn as shade 3
+    memory.write_byte(0xFF47, 0x0C);
+    run_to_line(&mut memory, 3);
+    assert_eq!(line(&memory, 3)[0..16], [[0; 8], [3; 8]].concat());
+
+    // background disabled, so the line is blank
+    memory.write_byte(0xFF40, 0x98);
+    run_to_line(&mut memory, 4);
+    assert_eq!(line(&memory, 4), [0; SCREEN_WIDTH]);
+}
+
+#[test]
+fn ppu_scroll() {
+    let mut memory = tile_memory();
+    memory.write_byte(0x9800, 0x01); // top left
+    memory.write_byte(0x9800 + 31, 0x01); // top right
+    memory.write_byte(0x9800 + 31 * 32 + 2, 0x01); // bottom row
+
+    memory.write_byte(0xFF43, 0xFC); // SCX, 4 pixels before the right edge of the map
+    memory.write_byte(0xFF40, 0x91);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..16], [vec![1; 12], vec![0; 4]].concat());
+
+    // wraps vertically to the bottom row of the map
+    memory.write_byte(0xFF43, 0x10);
+    memory.write_byte(0xFF42, 0xF7); // SCY, so line 1 is map row 248
+    run_to_line(&mut memory, 1);
+    assert_eq!(line(&memory, 1)[0..8], [1; 8]);
+    assert_eq!(line(&memory, 1)[8..16], [0; 8]);
+}
+
+#[test]
+fn ppu_window() {
+    let mut memory = tile_memory();
+    memory.write_byte(0x9C00, 0x01); // window's first tile
+    memory.write_byte(0x9C00 + 32, 0x81); // window's second row of tiles
+    memory.write_byte(0xFF4A, 0x02); // WY
+    memory.write_byte(0xFF4B, 0x07 + 80); // WX
+    memory.write_byte(0xFF40, 0xF1); // window on, using 0x9C00
+
+    run_to_line(&mut memory, 1);
+    assert_eq!(line(&memory, 1), [0; SCREEN_WIDTH]); // above WY
+    run_to_line(&mut memory, 2);
+    assert_eq!(line(&memory, 2)[72..96], [[0; 8], [1; 8], [0; 8]].concat());
+
+    // lines with the window hidden don't advance its line counter
+    memory.write_byte(0xFF40, 0xD1);
+    run_to_line(&mut memory, 9);
+    memory.write_byte(0xFF40, 0xF1);
+    for ly in 10..17 {
+        run_to_line(&mut memory, ly);
+        assert_eq!(line(&memory, ly as usize)[80], 1, "line {}", ly);
+    }
+    run_to_line(&mut memory, 17);
+    assert_eq!(line(&memory, 17)[80], 3); // window line 8, from the second row of tiles
+
+    // the line counter restarts each frame
+    run_to_line(&mut memory, 143);
+    run_to_line(&mut memory, 2);
+    assert_eq!(line(&memory, 2)[80], 1);
+}
+
+#[test]
+fn ppu_lcd_off_blank() {
+    let mut memory = tile_memory();
+    memory.write_byte(0x9800, 0x01);
+    memory.write_byte(0xFF40, 0x91);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0], 1);
+    memory.write_byte(0xFF40, 0x11);
+    assert!(memory.ppu.framebuffer().iter().all(|shade| *shade == 0));
+}
+
+/// Memory with object palettes set, and tiles at 0x8000 filled with rows of the given low and high bytes:
+/// tile 1 color 1, tile 2 color 2 on its left half, tile 3 color 3, tiles 4 and 5 colors 1 and 3
+fn object_memory() -> Memory {
+    let mut memory = Memory::new();
+    for (tile, (low, high)) in [(1, (0xFF, 0x00)), (2, (0x00, 0xF0)), (3, (0xFF, 0xFF)), (4, (0xFF, 0x00)), (5, (0xFF, 0xFF))] {
+        for row in 0..8 {
+            memory.write_byte(0x8000 + tile * 16 + row * 2, low);
+            memory.write_byte(0x8000 + tile * 16 + row * 2 + 1, high);
+        }
+    }
+    memory.write_byte(0xFF47, 0xE4); // BGP
+    memory.write_byte(0xFF48, 0xE4); // OBP0, colors map to the same shades
+    memory.write_byte(0xFF49, 0x0C); // OBP1, color 1 drawn as shade 3
+    memory
+}
+
+fn write_object(memory: &mut Memory, index: u16, y: u8, x: u8, tile: u8, attributes: u8) {
+    for (byte, value) in [y, x, tile, attributes].into_iter().enumerate() {
+        memory.write_byte(0xFE00 + index * 4 + byte as u16, value);
+    }
+}
+
+/// Expected row from runs of (shade, length)
+fn row(runs: &[(u8, usize)]) -> Vec<u8> {
+    runs.iter().flat_map(|&(shade, len)| vec![shade; len]).collect()
+}
+
+#[test]
+fn ppu_objects() {
+    let mut memory = object_memory();
+    write_object(&mut memory, 0, 16, 8, 2, 0x00);
+    write_object(&mut memory, 1, 16, 20, 2, 0x20); // X flip
+    write_object(&mut memory, 2, 16, 30, 1, 0x10); // OBP1
+    write_object(&mut memory, 3, 16, 0, 3, 0x00); // off the left edge
+    write_object(&mut memory, 4, 16, 168, 3, 0x00); // off the right edge
+
+    memory.write_byte(0xFF40, 0x93);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..32], row(&[(2, 4), (0, 12), (2, 4), (0, 2), (3, 8), (0, 2)]));
+    assert_eq!(line(&memory, 0)[152..], [0; 8]);
+    run_to_line(&mut memory, 8); // below the objects
+    assert_eq!(line(&memory, 8), [0; SCREEN_WIDTH]);
+
+    // LCDC bit 1 hides objects
+    memory.write_byte(0xFF40, 0x91);
+    run_to_line(&mut memory, 143);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0), [0; SCREEN_WIDTH]);
+}
+
+#[test]
+fn ppu_object_priority() {
+    let mut memory = object_memory();
+    // the lower X is on top, showing the other through its transparent pixels
+    write_object(&mut memory, 0, 16, 12, 1, 0x00);
+    write_object(&mut memory, 1, 16, 8, 2, 0x00);
+    // at the same X, the one earlier in OAM is on top
+    write_object(&mut memory, 2, 16, 40, 3, 0x00);
+    write_object(&mut memory, 3, 16, 40, 1, 0x00);
+    write_object(&mut memory, 4, 16, 56, 1, 0x00);
+    write_object(&mut memory, 5, 16, 56, 3, 0x00);
+
+    memory.write_byte(0xFF40, 0x93);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..56], row(&[(2, 4), (1, 8), (0, 20), (3, 8), (0, 8), (1, 8)]));
+}
+
+#[test]
+fn ppu_object_background_priority() {
+    let mut memory = object_memory();
+    memory.write_byte(0x9800, 0x01); // background color 1 on the first tile, 0 elsewhere
+    memory.write_byte(0xFF47, 0x03); // BGP, color 0 drawn as shade 3 and color 1 as shade 0
+    memory.write_byte(0xFF48, 0x40); // OBP0, color 3 drawn as shade 1
+    write_object(&mut memory, 0, 16, 12, 3, 0x80); // behind the background
+
+    // hidden by background color 1, but not by color 0 whatever its shade
+    memory.write_byte(0xFF40, 0x93);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..16], row(&[(0, 8), (1, 4), (3, 4)]));
+
+    // with the background disabled, the object is drawn in full
+    memory.write_byte(0xFF40, 0x92);
+    run_to_line(&mut memory, 1);
+    assert_eq!(line(&memory, 1)[0..16], row(&[(0, 4), (1, 8), (0, 4)]));
+}
+
+#[test]
+fn ppu_objects_per_line() {
+    let mut memory = object_memory();
+    // objects off screen still count, so only the first 9 on screen are drawn
+    write_object(&mut memory, 0, 16, 0, 1, 0x00);
+    for index in 1..11 {
+        write_object(&mut memory, index, 16, 8 * index as u8, 1, 0x00);
+    }
+    // the limit is per line, so this is hidden where it overlaps the others' lines, but not below them
+    write_object(&mut memory, 11, 20, 100, 1, 0x00);
+
+    memory.write_byte(0xFF40, 0x93);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..80], row(&[(1, 72), (0, 8)]));
+    run_to_line(&mut memory, 4);
+    assert_eq!(line(&memory, 4)[92..100], [0; 8]);
+    run_to_line(&mut memory, 8);
+    assert_eq!(line(&memory, 8)[92..100], [1; 8]);
+}
+
+#[test]
+fn ppu_tall_objects() {
+    let mut memory = object_memory();
+    write_object(&mut memory, 0, 16, 8, 5, 0x00); // bit 0 of the tile is ignored
+    write_object(&mut memory, 1, 16, 16, 4, 0x40); // Y flip, covering both tiles
+
+    memory.write_byte(0xFF40, 0x97);
+    run_to_line(&mut memory, 0);
+    assert_eq!(line(&memory, 0)[0..16], row(&[(1, 8), (3, 8)]));
+    run_to_line(&mut memory, 8);
+    assert_eq!(line(&memory, 8)[0..16], row(&[(3, 8), (1, 8)]));
+    run_to_line(&mut memory, 16);
+    assert_eq!(line(&memory, 16)[0..16], [0; 16]);
+}
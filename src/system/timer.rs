@@ -1,10 +1,10 @@
+#[allow(non_snake_case)]
 pub struct Timer {
     // internal values
-    timer_counter: u16,
-    timer_cycles: u16,
-    divider_counter: u8,
+    counter: u16, // incremented every T-cycle, 4 per M-cycle, with DIV as its upper byte
+    overflowed: bool, // TIMA overflowed this M-cycle, and reads 0 until TMA is loaded in the next
+    reloading: bool, // TMA is being loaded into TIMA this M-cycle
     // memory mapped values
-    r_DIV: u8,
     r_TIMA: u8,
     r_TMA: u8,
     r_TAC: u8,
@@ -17,85 +17,90 @@ pub struct Timer {
 // FF06 - TMA: Timer Modulo
 // FF07 - TAC: Timer Control
 
+// T-cycles in an M-cycle, as passed to update_timestep
+const CYCLE_LEN: u16 = 4;
+
+#[allow(non_snake_case)]
 impl Timer {
     pub fn new() -> Timer {
         Timer {
-            timer_counter: 0,
-            divider_counter: 0,
-            timer_cycles: 1024,
-            r_DIV: 0,
+            counter: 0,
+            overflowed: false,
+            reloading: false,
             r_TIMA: 0,
             r_TMA: 0,
             r_TAC: 0,
         }
     }
 
-    fn timer_control(&mut self) {
-        let byte = self.r_TAC & 0x3; // Bits 0 and 1
-        self.timer_cycles = match byte {
-            // number of cycles to wait before updating timer
-            0x0 => 1024, // frequency 0x1000hz
-            0x1 => 16, // frequency 0x40000hz
-            0x2 => 64, // frequency 0x10000hz
-            0x3 => 256, // frequency 0x4000hz
-            _ => 1024
-        };
-        //println!("Timer Threshold: {}", self.timer_cycles);
+    // bit of the counter selected by TAC, whose falling edge increments TIMA
+    fn selected_bit(&self) -> u16 {
+        match self.r_TAC & 0x3 {
+            0x0 => 1 << 9, // frequency 0x1000hz
+            0x1 => 1 << 3, // frequency 0x40000hz
+            0x2 => 1 << 5, // frequency 0x10000hz
+            _ => 1 << 7, // frequency 0x4000hz
+        }
+    }
+
+    // selected bit ANDed with the timer enable, the signal TIMA is clocked from
+    fn signal(&self) -> bool {
+        self.timer_enabled() && self.counter & self.selected_bit() > 0
     }
 
     // returns True if Timer Interrupt is to be set, otherwise false
     pub fn update_timestep(&mut self, cycles: u8) -> bool {
-        self.divider_inc(cycles);
-        if self.timer_enabled() {
-            self.timer_counter = self.timer_counter.wrapping_add(cycles as u16);
-            //println!("Counter val: {}", self.timer_counter );
-            if self.timer_counter >= self.timer_cycles {
-                //println!("Incrementing");
-                self.timer_counter = 0;
-                return self.timer_inc();
+        let mut interrupt = false;
+        for _ in 0..cycles {
+            // TMA is loaded and the interrupt raised one M-cycle after the overflow
+            self.reloading = self.overflowed;
+            if self.overflowed {
+                self.overflowed = false;
+                self.r_TIMA = self.r_TMA;
+                interrupt = true;
             }
-            
+            let before = self.signal();
+            self.counter = self.counter.wrapping_add(CYCLE_LEN);
+            self.falling_edge(before);
         }
-        false
+        interrupt
     }
 
-    fn timer_inc(&mut self) -> bool {
-        if self.r_TIMA == 0xFF {
-            // Overflow, so reset to modulo and raise Timer Interrupt
-            // println!("Overflow");
-            self.r_TIMA = self.r_TMA;
-            return true;
-        }
-        else {
-            self.r_TIMA = self.r_TIMA.wrapping_add(1);
-            //println!("Timer Value: {}", self.r_TIMA);
-        }
-        false
+    // increments TIMA if the signal fell from before
+    fn falling_edge(&mut self, before: bool) {
+        if before && !self.signal() { self.timer_inc() }
     }
 
-    fn divider_inc(&mut self, cycles: u8) {
-        // DIV incremented at 0x4000hz always
-        self.divider_counter = self.divider_counter.wrapping_add(cycles);
-        if self.divider_counter >= 255 {
-            self.divider_counter = 0;
-            self.r_DIV = self.r_DIV.wrapping_add(1); // resets to 0 when overflowing from 0xFF, no interrupt raised
-        }
+    fn timer_inc(&mut self) {
+        // Overflow leaves TIMA at 0 until the reload in the next M-cycle
+        self.overflowed = self.r_TIMA == 0xFF;
+        self.r_TIMA = self.r_TIMA.wrapping_add(1);
     }
 
     pub fn timer_enabled(&self) -> bool {
         self.r_TAC & 0x4 > 0 // bit 2 of TAC set
     }
 
+    // any write to r_DIV resets the whole counter, which increments TIMA if the selected bit was set
     pub fn reset_DIV(&mut self) {
-        // any write to r_DIV resets it
-        self.r_DIV = 0;
+        let before = self.signal();
+        self.counter = 0;
+        self.falling_edge(before);
+    }
+
+    pub fn set_DIV(&mut self, val: u8) {
+        // only used to set up the power-on state, as writes from the CPU reset DIV
+        self.counter = (val as u16) << 8;
     }
 
     pub fn get_DIV(&self) -> u8 {
-        self.r_DIV
+        (self.counter >> 8) as u8
     }
 
     pub fn set_TIMA(&mut self, val: u8) {
+        // ignored while TMA is being loaded, and cancels the reload if written before it
+        if self.reloading { return }
+        self.overflowed = false;
         self.r_TIMA = val;
     }
 
@@ -105,6 +110,8 @@ impl Timer {
 
     pub fn set_TMA(&mut self, val: u8) {
         self.r_TMA = val;
+        // written while being loaded, the new value goes to TIMA too
+        if self.reloading { self.r_TIMA = val }
     }
 
     pub fn get_TMA(&self) -> u8 {
@@ -112,11 +119,13 @@ impl Timer {
     }
 
     pub fn get_TAC(&self) -> u8 {
-        return 0x7 & self.r_TAC;
+        0x7 & self.r_TAC
     }
 
+    // disabling the timer or selecting another bit increments TIMA if the signal falls
     pub fn set_TAC(&mut self, val: u8) {
+        let before = self.signal();
         self.r_TAC = val & 0x7;
-        self.timer_control(); // update timer counter threshold
+        self.falling_edge(before);
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use super::{CPU, Memory};
+use super::{CPU, Memory, Timer};
 #[cfg(test)]
 
 #[test]
@@ -8,16 +8,18 @@ fn timer_inc() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
 
-    // enable timer and set it to update every 16 CPU cycles
+    // enable timer and set it to update every 16 T-cycles, or 4 M-cycles
     memory.write_byte(0xFF07, 0x5); // set TAC to 101
     assert_eq!(0x5, memory.timer.get_TAC());
 
     assert_eq!(memory.read_byte(0xFF05), 0x00);
 
-    // run CPU 16 steps, each instruction being interrupted as NOP (0x00)
-    for _ in 0..16 { 
-        cpu.run(&mut memory);
+    // run CPU 4 steps, each instruction being interrupted as NOP (0x00)
+    for _ in 0..3 {
+        cpu.run(&mut memory).unwrap();
     }
+    assert_eq!(memory.read_byte(0xFF05), 0x00);
+    cpu.run(&mut memory).unwrap();
 
     assert_eq!(memory.read_byte(0xFF05), 0x01);
 }
@@ -29,7 +31,7 @@ fn timer_overflow() {
     let mut cpu = CPU::new();
     let mut memory = Memory::new();
 
-    // enable timer and set it to update every 16 CPU cycles
+    // enable timer and set it to update every 4 M-cycles
     memory.write_byte(0xFF07, 0x5); // set TAC to 101
     memory.write_byte(0xFFFF, 0xFF); // enable interrupts
     assert_eq!(0x5, memory.timer.get_TAC());
@@ -37,15 +39,186 @@ fn timer_overflow() {
     assert_eq!(memory.read_byte(0xFF05), 0x00);
 
     // run until timer should overflow
-    for _ in 0..((16*0xFF)) { 
-        cpu.run(&mut memory);
+    for _ in 0..(4*0xFF) {
+        cpu.run(&mut memory).unwrap();
     }
     assert_eq!(memory.read_byte(0xFF05), 0xFF);
 
-    for _ in 0..16 { 
+    for _ in 0..4 {
         assert_eq!(memory.read_byte(0xFF05), 0xFF);
-        cpu.run(&mut memory);
+        cpu.run(&mut memory).unwrap();
     }
+    // TIMA reads 0 for a cycle before TMA is loaded and the interrupt raised
     assert_eq!(memory.read_byte(0xFF05), 0x00);
+    assert_eq!(cpu.get_pc(), 0x400);
+
+    cpu.run(&mut memory).unwrap();
+    // reloaded with TMA, then incremented during the 5 cycles of the interrupt dispatch
+    assert_eq!(memory.read_byte(0xFF05), 0x01);
     assert_eq!(cpu.get_pc(), 0x50); // Timer interrupt recognized by CPU
-}
\ No newline at end of file
+}
+
+#[test]
+fn timer_reload_delay() {
+    // TMA is loaded into TIMA, and the interrupt requested, one cycle after the overflow
+
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+
+    // enable timer and set it to update every 4 M-cycles
+    memory.write_byte(0xFF07, 0x5); // set TAC to 101
+    memory.write_byte(0xFF05, 0xFF);
+    memory.write_byte(0xFF06, 0x80);
+
+    for _ in 0..4 {
+        cpu.run(&mut memory).unwrap();
+    }
+    assert_eq!(memory.read_byte(0xFF05), 0x00);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE0);
+
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(memory.read_byte(0xFF05), 0x80);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE4);
+}
+
+/// LD A,d8, LD HL,d16 and a NOP, then LD (HL),A writing 0x33 on cycle 8, when TIMA overflows,
+/// or later with extra NOPs
+fn overflow_during_write(addr: u16, extra_nops: u16) -> (CPU, Memory) {
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+
+    // enable timer and set it to update every 4 M-cycles, overflowing on cycle 8
+    memory.write_byte(0xFF07, 0x5); // set TAC to 101
+    memory.write_byte(0xFF05, 0xFE);
+    memory.write_byte(0xFF06, 0x80);
+    memory.poke(0, 0x3E); // LD A,d8
+    memory.poke(1, 0x33);
+    memory.poke(2, 0x21); // LD HL,d16
+    memory.poke(3, addr as u8);
+    memory.poke(4, (addr >> 8) as u8);
+    memory.poke(6 + extra_nops, 0x77); // LD (HL),A
+
+    for _ in 0..4 + extra_nops {
+        cpu.run(&mut memory).unwrap();
+    }
+    (cpu, memory)
+}
+
+#[test]
+fn timer_reload_tima_write() {
+    // writing TIMA in the cycle it reads 0 cancels the reload and interrupt
+    let (mut cpu, mut memory) = overflow_during_write(0xFF05, 0);
+    assert_eq!(memory.read_byte(0xFF05), 0x33);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(memory.read_byte(0xFF05), 0x33);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE0);
+
+    // writing TIMA in the cycle TMA is loaded is ignored
+    let (_, memory) = overflow_during_write(0xFF05, 1);
+    assert_eq!(memory.read_byte(0xFF05), 0x80);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE4);
+}
+
+#[test]
+fn timer_reload_tma_write() {
+    // writing TMA in the cycle it's loaded also loads the new value
+    let (_, memory) = overflow_during_write(0xFF06, 1);
+    assert_eq!(memory.read_byte(0xFF05), 0x33);
+    assert_eq!(memory.read_byte(0xFF0F), 0xE4);
+
+    // written before then, TMA is loaded as usual
+    let (mut cpu, mut memory) = overflow_during_write(0xFF06, 0);
+    assert_eq!(memory.read_byte(0xFF05), 0x00);
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(memory.read_byte(0xFF05), 0x33);
+}
+
+#[test]
+fn timer_mid_instruction() {
+    // each bus access of an instruction happens in its own cycle, so the timer
+    // can advance between the read and write of a read-modify-write instruction
+
+    let mut cpu = CPU::new();
+    let mut memory = Memory::new();
+
+    // enable timer and set it to update every 4 M-cycles
+    memory.write_byte(0xFF07, 0x5); // set TAC to 101
+    memory.poke(0, 0x21); // LD HL,d16
+    memory.poke(1, 0x05); // HL points to TIMA
+    memory.poke(2, 0xFF);
+    memory.poke(5, 0x34); // INC (HL)
+
+    // LD HL,d16 and 2 NOPs, taking 5 cycles
+    for _ in 0..3 {
+        cpu.run(&mut memory).unwrap();
+    }
+    assert_eq!(memory.read_byte(0xFF05), 0x01);
+
+    // TIMA read as 1 on cycle 7, and incremented by the timer on cycle 8,
+    // immediately before the incremented value read earlier is written back
+    cpu.run(&mut memory).unwrap();
+    assert_eq!(cpu.get_cycles(), 3);
+    assert_eq!(memory.read_byte(0xFF05), 0x02);
+}
+
+#[test]
+fn timer_div_rate() {
+    // DIV is the upper byte of a counter incremented every T-cycle, so it ticks every 64 M-cycles
+    let mut timer = Timer::new();
+    timer.update_timestep(63);
+    assert_eq!(timer.get_DIV(), 0x00);
+    timer.update_timestep(1);
+    assert_eq!(timer.get_DIV(), 0x01);
+    for _ in 0..255 { timer.update_timestep(64); }
+    assert_eq!(timer.get_DIV(), 0x00); // wraps without an interrupt
+}
+
+#[test]
+fn timer_div_write_glitch() {
+    // resetting the counter while the selected bit is set is a falling edge
+    let mut timer = Timer::new();
+    timer.set_TAC(0x4); // every 1024 T-cycles, from bit 9
+    timer.update_timestep(128); // bit 9 set
+    assert_eq!(timer.get_TIMA(), 0x00);
+    timer.reset_DIV();
+    assert_eq!(timer.get_TIMA(), 0x01);
+
+    // with the bit clear, nothing happens
+    timer.update_timestep(127);
+    timer.reset_DIV();
+    assert_eq!(timer.get_TIMA(), 0x01);
+
+    // the edge can overflow TIMA, with the reload in the following cycle
+    timer.set_TIMA(0xFF);
+    timer.set_TMA(0x80);
+    timer.update_timestep(128);
+    timer.reset_DIV();
+    assert_eq!(timer.get_TIMA(), 0x00);
+    assert!(timer.update_timestep(1));
+    assert_eq!(timer.get_TIMA(), 0x80);
+}
+
+#[test]
+fn timer_tac_glitch() {
+    let mut timer = Timer::new();
+    timer.set_TAC(0x5); // bit 3
+    timer.update_timestep(2); // counter is 8, bit 3 set
+    assert_eq!(timer.get_TIMA(), 0x00);
+
+    // disabling the timer while the bit is set increments TIMA
+    timer.set_TAC(0x1);
+    assert_eq!(timer.get_TIMA(), 0x01);
+
+    // so does selecting a bit that's clear
+    timer.set_TAC(0x5);
+    timer.set_TAC(0x6); // bit 5 clear
+    assert_eq!(timer.get_TIMA(), 0x02);
+
+    // selecting another set bit doesn't
+    timer.update_timestep(6); // counter is 32, bit 5 set
+    timer.set_TAC(0x6);
+    assert_eq!(timer.get_TIMA(), 0x02);
+    timer.update_timestep(4); // counter is 48, bits 4 and 5 set
+    timer.set_TAC(0x7); // bit 7 clear
+    assert_eq!(timer.get_TIMA(), 0x03);
+}